@@ -1,12 +1,13 @@
 use crate::{Error, Result};
-use base64::{engine::general_purpose, Engine as _};
 use bytes::Bytes;
-use reqwest::{Client, Method, RequestBuilder as ReqwestBuilder, Response};
+use reqwest::{header::HeaderMap, Client, Method, RequestBuilder as ReqwestBuilder, Response};
 use serde::{de::DeserializeOwned, Serialize};
 
+#[allow(clippy::upper_case_acronyms)]
 pub enum RequestMethod {
     GET,
     POST,
+    PATCH,
     DELETE,
 }
 
@@ -15,11 +16,13 @@ impl RequestMethod {
         match self {
             RequestMethod::GET => Method::GET,
             RequestMethod::POST => Method::POST,
+            RequestMethod::PATCH => Method::PATCH,
             RequestMethod::DELETE => Method::DELETE,
         }
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub enum ContentType {
     JSON,
 }
@@ -35,7 +38,17 @@ impl ContentType {
 pub trait Pagination: DeserializeOwned {
     fn page_limit(&self) -> u64;
     fn get_pagination_url(&self, url: &str, page_index: &u64) -> String;
+    #[allow(dead_code)]
     fn next(&self) -> &Option<String>;
+
+    /// Builds the request for another page, e.g. to add authentication.
+    fn page_request(&self, url: &str) -> Result<RequestBuilder> {
+        Ok(request_builder(RequestMethod::GET, url))
+    }
+
+    /// Carries over state that isn't in a page's body, like credentials,
+    /// and reads anything the API returns in headers instead.
+    fn inherit(&mut self, _previous: &Self, _headers: &HeaderMap) {}
 }
 
 pub struct APIResult<T> {
@@ -50,18 +63,23 @@ where
     pub async fn get_page_from_index(&self, entry_index: &u64) -> Result<APIResult<T>> {
         let page_index = entry_index / self.response.page_limit();
         let url = self.response.get_pagination_url(&self.url, &page_index);
-        request_builder(RequestMethod::GET, &url)
-            .request_model()
-            .await
+        self.fetch_page(&url).await
+    }
+
+    async fn fetch_page(&self, url: &str) -> Result<APIResult<T>> {
+        let (mut page, headers) = self
+            .response
+            .page_request(url)?
+            .request_model_with_headers::<T>()
+            .await?;
+        page.response.inherit(&self.response, &headers);
+        Ok(page)
     }
 
+    #[allow(dead_code)]
     pub async fn next_page(&self) -> Result<Option<APIResult<T>>> {
         match self.response.next() {
-            Some(url) => Ok(Some(
-                request_builder(RequestMethod::GET, &url)
-                    .request_model()
-                    .await?,
-            )),
+            Some(url) => Ok(Some(self.fetch_page(url).await?)),
             None => Ok(None),
         }
     }
@@ -90,6 +108,12 @@ impl RequestBuilder {
         self
     }
 
+    /// A pre-serialized body, e.g. JSON built up incrementally.
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.request = self.request.body(body);
+        self
+    }
+
     pub fn header(mut self, key: &str, val: &str) -> Self {
         self.request = self.request.header(key, val);
         self
@@ -117,17 +141,29 @@ impl RequestBuilder {
     }
 
     pub async fn request_model<T>(self) -> Result<APIResult<T>>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(self.request_model_with_headers().await?.0)
+    }
+
+    /// Like `request_model`, also returning the response headers.
+    pub async fn request_model_with_headers<T>(self) -> Result<(APIResult<T>, HeaderMap)>
     where
         T: DeserializeOwned,
     {
         let url = self.url.clone();
         let res = self.request().await?;
+        let headers = res.headers().clone();
         let text = res.text().await?;
         let model: T = serde_json::from_str(&text)?;
-        Ok(APIResult {
-            url,
-            response: model,
-        })
+        Ok((
+            APIResult {
+                url,
+                response: model,
+            },
+            headers,
+        ))
     }
 
     pub async fn request_bytes(self) -> Result<Bytes> {
@@ -135,12 +171,6 @@ impl RequestBuilder {
         let bytes = res.bytes().await?;
         Ok(bytes)
     }
-
-    pub async fn request_b64(self) -> Result<String> {
-        let bytes = self.request_bytes().await?;
-        let b64 = general_purpose::STANDARD.encode(bytes);
-        Ok(b64)
-    }
 }
 
 pub fn request_builder(method: RequestMethod, url: &str) -> RequestBuilder {
@@ -1,10 +1,13 @@
 use super::base::{request_builder, APIResult, Pagination, RequestMethod};
-use crate::Result;
+use super::supabase::encode;
+use crate::{config, Result};
+use bytes::Bytes;
 use serde::{de::DeserializeOwned, Deserialize};
 
 #[derive(Debug, Deserialize)]
 pub struct DeezerPaginationResponse<T> {
     pub data: T,
+    #[allow(dead_code)]
     pub next: Option<String>,
     pub total: u64,
 }
@@ -14,7 +17,8 @@ where
     T: DeserializeOwned,
 {
     fn get_pagination_url(&self, url: &str, page_index: &u64) -> String {
-        format!("{url}&index={page_index}")
+        // Deezer's `index` is the offset of the first result, not a page number
+        format!("{url}&index={}", page_index * self.page_limit())
     }
 
     fn page_limit(&self) -> u64 {
@@ -58,12 +62,37 @@ pub struct Artist {
 }
 
 pub async fn search_tracks(query: &str) -> Result<APIResult<DeezerPaginationResponse<TrackList>>> {
-    let url = format!("https://api.deezer.com/search/track?q={query}");
+    let url = format!(
+        "{}/search/track?q={}",
+        config::get()?.deezer_url,
+        encode(query)
+    );
     request_builder(RequestMethod::GET, &url)
         .request_model::<DeezerPaginationResponse<TrackList>>()
         .await
 }
 
-pub async fn encoded_preview(url: &str) -> Result<String> {
-    request_builder(RequestMethod::GET, url).request_b64().await
+pub async fn get_track(id: u64) -> Result<APIResult<Track>> {
+    let url = format!("{}/track/{id}", config::get()?.deezer_url);
+    request_builder(RequestMethod::GET, &url)
+        .request_model::<Track>()
+        .await
+}
+
+/// Parses the unix expiry time out of a signed preview URL, e.g.
+/// `...mp3?hdnea=exp=1731000000~acl=/api/preview/*~hmac=...`.
+pub fn preview_expiry(url: &str) -> Option<u64> {
+    let token = url.split_once("hdnea=")?.1;
+    let expiry = token.split_once("exp=")?.1;
+    expiry
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+pub async fn download(url: &str) -> Result<Bytes> {
+    request_builder(RequestMethod::GET, url)
+        .request_bytes()
+        .await
 }
@@ -1,44 +1,94 @@
 use super::base::{request_builder, APIResult, RequestMethod};
-use crate::Result;
+use crate::{config, language::Language, Result};
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
-const DICTIONARY_URL: &str = "https://api.dictionaryapi.dev/api/v2/";
-
 pub type Words = Vec<Word>;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Word {
     pub word: String,
+    pub phonetic: Option<String>,
+
+    #[serde(default)]
+    pub phonetics: Vec<Phonetic>,
     pub origin: Option<String>,
     pub meanings: Option<Vec<Meaning>>,
+
+    #[serde(rename = "sourceUrls", default)]
+    pub source_urls: Vec<String>,
 }
 
 impl Word {
     pub fn unknown(word: String) -> Self {
         Self {
             word,
+            phonetic: None,
+            phonetics: Vec::new(),
             origin: None,
             meanings: None,
+            source_urls: Vec::new(),
+        }
+    }
+
+    /// URL of the first phonetic with a recording. Some are protocol-relative.
+    pub fn pronunciation_url(&self) -> Option<String> {
+        let audio = self
+            .phonetics
+            .iter()
+            .filter_map(|p| p.audio.as_deref())
+            .find(|audio| !audio.is_empty())?;
+        match audio.strip_prefix("//") {
+            Some(rest) => Some(format!("https://{rest}")),
+            None => Some(audio.to_string()),
         }
     }
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Phonetic {
+    pub text: Option<String>,
+    pub audio: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Meaning {
     #[serde(rename = "partOfSpeech")]
     pub part_of_speech: String,
     pub definitions: Vec<Definition>,
+
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+
+    #[serde(default)]
+    pub antonyms: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Definition {
     pub definition: String,
     pub example: Option<String>,
+
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+
+    #[serde(default)]
+    pub antonyms: Vec<String>,
 }
 
-pub async fn search_dictionary(word: &str) -> Result<APIResult<Words>> {
-    let url = format!("{DICTIONARY_URL}entries/en/{word}");
+pub async fn search_dictionary(word: &str, language: Language) -> Result<APIResult<Words>> {
+    let url = format!(
+        "{}/entries/{}/{word}",
+        config::get()?.dictionary_url,
+        language.code()
+    );
     request_builder(RequestMethod::GET, &url)
         .request_model::<Words>()
         .await
 }
+
+pub async fn download(url: &str) -> Result<Bytes> {
+    request_builder(RequestMethod::GET, url)
+        .request_bytes()
+        .await
+}
@@ -1,30 +1,346 @@
-use super::base::{request_builder, ContentType, RequestBuilder, RequestMethod};
-use crate::Result;
-use serde::{de::DeserializeOwned, Serialize};
-use std::env;
+use super::base::{
+    request_builder, APIResult, ContentType, Pagination, RequestBuilder, RequestMethod,
+};
+use crate::{config, Error, Result};
+use log::warn;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::HeaderMap;
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
+use std::{collections::BTreeMap, env, ops::Range};
 
-fn supabase_request_builder(method: RequestMethod, url: &str, key: &str) -> Result<RequestBuilder> {
+/// `prefer` holds `Prefer` preferences on top of returning the affected rows.
+fn supabase_request_builder(
+    method: RequestMethod,
+    url: &str,
+    key: &str,
+    prefer: &[&str],
+) -> Result<RequestBuilder> {
+    let prefer = ["return=representation"]
+        .iter()
+        .chain(prefer)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(",");
     let mut builder = request_builder(method, url);
     builder = builder
         .header("apikey", key)
         .bearer(key)
         .content_type(ContentType::JSON)
-        .header("Prefer", "return=representation");
+        .header("Prefer", &prefer);
     Ok(builder)
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub enum OrderDirection {
+    ASC,
     DESC,
 }
 
 impl OrderDirection {
     fn as_str(&self) -> &'static str {
         match self {
+            OrderDirection::ASC => "asc",
             OrderDirection::DESC => "desc",
         }
     }
 }
 
+#[allow(dead_code)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+struct Order {
+    column: String,
+    direction: OrderDirection,
+    nulls: Option<Nulls>,
+}
+
+impl Order {
+    fn render(&self) -> String {
+        let mut order = format!("{}.{}", encode(&self.column), self.direction.as_str());
+        match self.nulls {
+            Some(Nulls::First) => order.push_str(".nullsfirst"),
+            Some(Nulls::Last) => order.push_str(".nullslast"),
+            None => {}
+        }
+        order
+    }
+}
+
+/// The values `is` can test for.
+#[allow(dead_code)]
+pub enum IsValue {
+    Null,
+    True,
+    False,
+    Unknown,
+}
+
+impl IsValue {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IsValue::Null => "null",
+            IsValue::True => "true",
+            IsValue::False => "false",
+            IsValue::Unknown => "unknown",
+        }
+    }
+}
+
+/// A PostgREST row filter. Values are quoted and percent-encoded when the
+/// query is built, so they may contain any characters.
+#[allow(dead_code)]
+pub enum Filter {
+    Eq(String, String),
+    Neq(String, String),
+    Gt(String, String),
+    Gte(String, String),
+    Lt(String, String),
+    Lte(String, String),
+
+    /// `*` matches any run of characters.
+    Like(String, String),
+    Ilike(String, String),
+    Is(String, IsValue),
+    In(String, Vec<String>),
+    Not(Box<Filter>),
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+}
+
+/// Everything but unreserved characters, so values can't break out of
+/// their query parameter.
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Percent-encodes `s` for use as a query parameter value.
+pub(crate) fn encode(s: &str) -> String {
+    utf8_percent_encode(s, QUERY_VALUE).to_string()
+}
+
+/// Double-quotes a value inside an `in` list or logic group, where commas,
+/// dots, colons and parentheses are otherwise structural.
+fn quote(value: &str) -> String {
+    let reserved = |c: char| ",.:()\"\\".contains(c) || c.is_whitespace();
+    if value.is_empty() || value.contains(reserved) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[allow(dead_code)]
+impl Filter {
+    pub fn eq(column: &str, value: impl ToString) -> Self {
+        Self::Eq(column.into(), value.to_string())
+    }
+
+    pub fn neq(column: &str, value: impl ToString) -> Self {
+        Self::Neq(column.into(), value.to_string())
+    }
+
+    pub fn gt(column: &str, value: impl ToString) -> Self {
+        Self::Gt(column.into(), value.to_string())
+    }
+
+    pub fn gte(column: &str, value: impl ToString) -> Self {
+        Self::Gte(column.into(), value.to_string())
+    }
+
+    pub fn lt(column: &str, value: impl ToString) -> Self {
+        Self::Lt(column.into(), value.to_string())
+    }
+
+    pub fn lte(column: &str, value: impl ToString) -> Self {
+        Self::Lte(column.into(), value.to_string())
+    }
+
+    pub fn like(column: &str, pattern: &str) -> Self {
+        Self::Like(column.into(), pattern.into())
+    }
+
+    pub fn ilike(column: &str, pattern: &str) -> Self {
+        Self::Ilike(column.into(), pattern.into())
+    }
+
+    pub fn is(column: &str, value: IsValue) -> Self {
+        Self::Is(column.into(), value)
+    }
+
+    pub fn in_values<I, V>(column: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: ToString,
+    {
+        Self::In(
+            column.into(),
+            values.into_iter().map(|v| v.to_string()).collect(),
+        )
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(filter: Filter) -> Self {
+        Self::Not(Box::new(filter))
+    }
+
+    /// The operator and raw value, e.g. `eq.foo`, with the value quoted for
+    /// use inside a logic group if `grouped`.
+    fn operation(&self, grouped: bool) -> String {
+        let value = |v: &str| if grouped { quote(v) } else { v.to_string() };
+        match self {
+            Filter::Eq(_, v) => format!("eq.{}", value(v)),
+            Filter::Neq(_, v) => format!("neq.{}", value(v)),
+            Filter::Gt(_, v) => format!("gt.{}", value(v)),
+            Filter::Gte(_, v) => format!("gte.{}", value(v)),
+            Filter::Lt(_, v) => format!("lt.{}", value(v)),
+            Filter::Lte(_, v) => format!("lte.{}", value(v)),
+            Filter::Like(_, v) => format!("like.{}", value(v)),
+            Filter::Ilike(_, v) => format!("ilike.{}", value(v)),
+            Filter::Is(_, v) => format!("is.{}", v.as_str()),
+            Filter::In(_, vs) => {
+                let values: Vec<String> = vs.iter().map(|v| quote(v)).collect();
+                format!("in.({})", values.join(","))
+            }
+            Filter::Not(filter) => format!("not.{}", filter.operation(grouped)),
+            Filter::Or(filters) => format!("or({})", Self::group(filters)),
+            Filter::And(filters) => format!("and({})", Self::group(filters)),
+        }
+    }
+
+    fn column(&self) -> Option<&str> {
+        match self {
+            Filter::Eq(c, _)
+            | Filter::Neq(c, _)
+            | Filter::Gt(c, _)
+            | Filter::Gte(c, _)
+            | Filter::Lt(c, _)
+            | Filter::Lte(c, _)
+            | Filter::Like(c, _)
+            | Filter::Ilike(c, _)
+            | Filter::Is(c, _)
+            | Filter::In(c, _) => Some(c),
+            Filter::Not(filter) => filter.column(),
+            Filter::Or(_) | Filter::And(_) => None,
+        }
+    }
+
+    /// Filters inside `or(...)`/`and(...)`, e.g. `a.eq.1,b.gt.2`.
+    fn group(filters: &[Filter]) -> String {
+        filters
+            .iter()
+            .map(|filter| match filter.column() {
+                Some(column) => format!("{}.{}", column, filter.operation(true)),
+                None => filter.operation(true),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// The filter as an encoded query parameter, e.g. `name=eq.foo%20bar`
+    /// or `or=%28a.eq.1%2Cb.eq.2%29`. PostgREST decodes the query string
+    /// before parsing it, so structural characters can be encoded too.
+    fn render(&self) -> String {
+        match self {
+            Filter::Or(filters) => format!("or={}", encode(&format!("({})", Self::group(filters)))),
+            Filter::And(filters) => {
+                format!("and={}", encode(&format!("({})", Self::group(filters))))
+            }
+            Filter::Not(filter) if filter.column().is_none() => format!("not.{}", filter.render()),
+            _ => format!(
+                "{}={}",
+                encode(self.column().unwrap_or_default()),
+                encode(&self.operation(false))
+            ),
+        }
+    }
+}
+
+/// Filter methods shared by the builders that target rows.
+#[allow(dead_code)]
+pub trait Filterable: Sized {
+    fn filters_mut(&mut self) -> &mut Vec<Filter>;
+
+    fn filter(mut self, filter: Filter) -> Self {
+        self.filters_mut().push(filter);
+        self
+    }
+
+    fn eq(self, column: &str, value: impl ToString) -> Self {
+        self.filter(Filter::eq(column, value))
+    }
+
+    fn neq(self, column: &str, value: impl ToString) -> Self {
+        self.filter(Filter::neq(column, value))
+    }
+
+    fn gt(self, column: &str, value: impl ToString) -> Self {
+        self.filter(Filter::gt(column, value))
+    }
+
+    fn gte(self, column: &str, value: impl ToString) -> Self {
+        self.filter(Filter::gte(column, value))
+    }
+
+    fn lt(self, column: &str, value: impl ToString) -> Self {
+        self.filter(Filter::lt(column, value))
+    }
+
+    fn lte(self, column: &str, value: impl ToString) -> Self {
+        self.filter(Filter::lte(column, value))
+    }
+
+    fn like(self, column: &str, pattern: &str) -> Self {
+        self.filter(Filter::like(column, pattern))
+    }
+
+    fn ilike(self, column: &str, pattern: &str) -> Self {
+        self.filter(Filter::ilike(column, pattern))
+    }
+
+    fn is(self, column: &str, value: IsValue) -> Self {
+        self.filter(Filter::is(column, value))
+    }
+
+    fn in_values<I, V>(self, column: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: ToString,
+    {
+        self.filter(Filter::in_values(column, values))
+    }
+
+    fn not(self, filter: Filter) -> Self {
+        self.filter(Filter::not(filter))
+    }
+
+    fn or(self, filters: Vec<Filter>) -> Self {
+        self.filter(Filter::Or(filters))
+    }
+
+    fn and(self, filters: Vec<Filter>) -> Self {
+        self.filter(Filter::And(filters))
+    }
+}
+
+/// Joins rendered filters and ordering into a query string, without the
+/// leading `?` or `&`.
+fn query_params(filters: &[Filter], orders: &[Order]) -> Vec<String> {
+    let mut params: Vec<String> = filters.iter().map(Filter::render).collect();
+    if !orders.is_empty() {
+        let orders: Vec<String> = orders.iter().map(Order::render).collect();
+        params.push(format!("order={}", orders.join(",")));
+    }
+    params
+}
+
 pub struct SupabaseClient {
     base_url: String,
     key: String,
@@ -32,7 +348,7 @@ pub struct SupabaseClient {
 
 impl SupabaseClient {
     pub fn new() -> Result<Self> {
-        let base_url = env::var("SUPABASE_URL")?;
+        let base_url = config::get()?.supabase_url.clone();
         let key = env::var("SUPABASE_RLS_KEY")?;
         Ok(Self { base_url, key })
     }
@@ -62,21 +378,225 @@ impl SupabaseTable {
     where
         J: Serialize + DeserializeOwned,
     {
-        let rows = vec![row];
+        self.insert_many(vec![row])
+    }
+
+    /// Inserts `rows`, split into batches by row count and payload size.
+    pub fn insert_many<J>(self, rows: Vec<J>) -> InsertBuilder<J>
+    where
+        J: Serialize + DeserializeOwned,
+    {
         InsertBuilder::new(self, rows)
     }
 
     pub fn delete(self) -> DeleteBuilder {
         DeleteBuilder::new(self)
     }
+
+    /// Sets `values`, typically a partial row, on every row matching the
+    /// builder's filters.
+    #[allow(dead_code)]
+    pub fn update<J>(self, values: J) -> UpdateBuilder<J>
+    where
+        J: Serialize,
+    {
+        UpdateBuilder::new(self, values)
+    }
+
+    /// Inserts `rows`, merging any that conflict with an existing row.
+    pub fn upsert<J>(self, rows: Vec<J>) -> UpsertBuilder<J>
+    where
+        J: Serialize + DeserializeOwned,
+    {
+        UpsertBuilder::new(self, rows)
+    }
+}
+
+/// A `select` projection: plain columns, JSON paths such as `track1->title`,
+/// aliases and embedded related tables.
+#[derive(Default)]
+pub struct Columns {
+    items: Vec<String>,
+}
+
+impl Columns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A column or JSON path. `->` keeps the JSON value, `->>` gives text.
+    pub fn column(mut self, column: &str) -> Self {
+        self.items.push(column.to_string());
+        self
+    }
+
+    /// A column or JSON path returned under the key `alias`.
+    pub fn alias(mut self, alias: &str, column: &str) -> Self {
+        self.items.push(format!("{alias}:{column}"));
+        self
+    }
+
+    /// Rows of the related `table`, nested under its name.
+    #[allow(dead_code)]
+    pub fn embed(mut self, table: &str, columns: Columns) -> Self {
+        self.items.push(format!("{table}({})", columns.render()));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn embed_as(mut self, alias: &str, table: &str, columns: Columns) -> Self {
+        self.items
+            .push(format!("{alias}:{table}({})", columns.render()));
+        self
+    }
+
+    fn render(&self) -> String {
+        if self.items.is_empty() {
+            "*".to_string()
+        } else {
+            self.items.join(",")
+        }
+    }
+}
+
+/// How PostgREST should count the total rows matching a select.
+#[allow(dead_code)]
+pub enum Count {
+    Exact,
+    Planned,
+    Estimated,
+}
+
+impl Count {
+    fn preference(&self) -> &'static str {
+        match self {
+            Count::Exact => "count=exact",
+            Count::Planned => "count=planned",
+            Count::Estimated => "count=estimated",
+        }
+    }
+}
+
+/// A `Content-Range` header, e.g. `0-24/3573`, `*/0` or `0-24/*`.
+#[allow(dead_code)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ContentRange {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    pub fn parse(header: &str) -> Option<Self> {
+        let (range, total) = header.trim().split_once('/')?;
+        let total = match total {
+            "*" => None,
+            total => Some(total.parse().ok()?),
+        };
+        let (from, to) = match range.split_once('-') {
+            Some((from, to)) => (Some(from.parse().ok()?), Some(to.parse().ok()?)),
+            None if range == "*" => (None, None),
+            None => return None,
+        };
+        Some(Self { from, to, total })
+    }
+}
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+/// A page of select results that can fetch its neighbours through
+/// `APIResult`, e.g. `get_page_from_index`.
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct SupabasePage<T> {
+    pub rows: Vec<T>,
+
+    #[serde(skip)]
+    pub range: ContentRange,
+
+    /// The select URL without `limit` and `offset`.
+    #[serde(skip)]
+    base_url: String,
+
+    #[serde(skip)]
+    key: String,
+
+    #[serde(skip)]
+    prefer: Vec<&'static str>,
+
+    #[serde(skip)]
+    limit: u64,
+
+    #[serde(skip)]
+    next: Option<String>,
+}
+
+impl<T> SupabasePage<T> {
+    /// Total matching rows, if a count was requested.
+    #[allow(dead_code)]
+    pub fn total(&self) -> Option<u64> {
+        self.range.total
+    }
+
+    fn page_url(&self, offset: u64) -> String {
+        format!("{}&limit={}&offset={}", self.base_url, self.limit, offset)
+    }
+
+    fn read_headers(&mut self, headers: &HeaderMap) {
+        self.range = headers
+            .get("Content-Range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(ContentRange::parse)
+            .unwrap_or_default();
+        let more = match (self.range.to, self.range.total) {
+            (Some(to), Some(total)) => to + 1 < total,
+            // Without a count, a full page suggests there may be more
+            (Some(_), None) => self.rows.len() as u64 == self.limit,
+            (None, _) => false,
+        };
+        self.next = match self.range.to {
+            Some(to) if more => Some(self.page_url(to + 1)),
+            _ => None,
+        };
+    }
+}
+
+impl<T> Pagination for SupabasePage<T>
+where
+    T: DeserializeOwned,
+{
+    fn page_limit(&self) -> u64 {
+        self.limit
+    }
+
+    fn get_pagination_url(&self, _url: &str, page_index: &u64) -> String {
+        self.page_url(page_index * self.limit)
+    }
+
+    fn next(&self) -> &Option<String> {
+        &self.next
+    }
+
+    fn page_request(&self, url: &str) -> Result<RequestBuilder> {
+        supabase_request_builder(RequestMethod::GET, url, &self.key, &self.prefer)
+    }
+
+    fn inherit(&mut self, previous: &Self, headers: &HeaderMap) {
+        self.base_url = previous.base_url.clone();
+        self.key = previous.key.clone();
+        self.prefer = previous.prefer.clone();
+        self.limit = previous.limit;
+        self.read_headers(headers);
+    }
 }
 
 pub struct SelectBuilder {
     table: SupabaseTable,
-    columns: Option<&'static str>,
+    columns: Option<Columns>,
+    filters: Vec<Filter>,
+    orders: Vec<Order>,
     limit: Option<u64>,
-    order_column: Option<&'static str>,
-    order_direction: Option<OrderDirection>,
+    offset: Option<u64>,
 }
 
 impl SelectBuilder {
@@ -84,15 +604,36 @@ impl SelectBuilder {
         Self {
             table,
             columns: None,
+            filters: Vec::new(),
+            orders: Vec::new(),
             limit: None,
-            order_column: None,
-            order_direction: None,
+            offset: None,
         }
     }
 
-    pub fn order(mut self, column: &'static str, direction: OrderDirection) -> Self {
-        self.order_column = Some(column);
-        self.order_direction = Some(direction);
+    /// Fetches only `columns` rather than every column.
+    pub fn columns(mut self, columns: Columns) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Orders by `column`, after any columns already ordered by.
+    pub fn order(mut self, column: &str, direction: OrderDirection) -> Self {
+        self.orders.push(Order {
+            column: column.into(),
+            direction,
+            nulls: None,
+        });
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn order_nulls(mut self, column: &str, direction: OrderDirection, nulls: Nulls) -> Self {
+        self.orders.push(Order {
+            column: column.into(),
+            direction,
+            nulls: Some(nulls),
+        });
         self
     }
 
@@ -101,26 +642,226 @@ impl SelectBuilder {
         self
     }
 
+    /// Rows `from` to `to`, inclusive and zero-based. A range ending before
+    /// it starts is empty.
+    #[allow(dead_code)]
+    pub fn range(mut self, from: u64, to: u64) -> Self {
+        self.offset = Some(from);
+        self.limit = Some(if to < from { 0 } else { to - from + 1 });
+        self
+    }
+
+    /// The select URL without `limit` and `offset`.
+    fn base_url(&self) -> String {
+        let columns = self.columns.as_ref().map(Columns::render);
+        let mut url = format!(
+            "{}?select={}",
+            self.table.table_url(),
+            encode(columns.as_deref().unwrap_or("*"))
+        );
+        for param in query_params(&self.filters, &self.orders) {
+            url.push_str(&format!("&{param}"));
+        }
+        url
+    }
+
     pub async fn request<T>(&self) -> Result<Vec<T>>
     where
         T: DeserializeOwned,
     {
-        let columns = self.columns.unwrap_or("*");
-        let mut url = format!("{}?select={}", self.table.table_url(), columns);
-
-        if let (Some(column), Some(direction)) = (self.order_column, &self.order_direction) {
-            url.push_str(&format!("&order={}.{}", column, direction.as_str()));
+        if self.limit == Some(0) {
+            return Ok(Vec::new());
         }
+        let mut url = self.base_url();
         if let Some(l) = self.limit {
             url.push_str(&format!("&limit={l}"));
         }
+        if let Some(o) = self.offset {
+            url.push_str(&format!("&offset={o}"));
+        }
         Ok(
-            supabase_request_builder(RequestMethod::GET, &url, &self.table.client.key)?
+            supabase_request_builder(RequestMethod::GET, &url, &self.table.client.key, &[])?
                 .request_model::<Vec<T>>()
                 .await?
                 .response,
         )
     }
+
+    /// Fetches one page of rows, `limit` long or `DEFAULT_PAGE_SIZE` if
+    /// unset, along with the total number of matching rows if `count` is
+    /// given. Counting is only offered here, since `request` has nowhere to
+    /// return it.
+    #[allow(dead_code)]
+    pub async fn request_page<T>(&self, count: Option<Count>) -> Result<APIResult<SupabasePage<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let key = self.table.client.key.clone();
+        let prefer: Vec<&'static str> = count.iter().map(Count::preference).collect();
+        let base_url = self.base_url();
+        let mut page = SupabasePage {
+            rows: Vec::new(),
+            range: ContentRange::default(),
+            base_url,
+            key,
+            prefer,
+            limit: DEFAULT_PAGE_SIZE,
+            next: None,
+        };
+        let limit = match self.limit {
+            // An empty range has no rows to fetch or pages to follow
+            Some(0) => {
+                return Ok(APIResult {
+                    url: page.page_url(0),
+                    response: page,
+                })
+            }
+            Some(limit) => limit,
+            None => DEFAULT_PAGE_SIZE,
+        };
+        page.limit = limit;
+        let url = page.page_url(self.offset.unwrap_or(0));
+        let (result, headers) =
+            supabase_request_builder(RequestMethod::GET, &url, &page.key, &page.prefer)?
+                .request_model_with_headers::<Vec<T>>()
+                .await?;
+        page.rows = result.response;
+        page.read_headers(&headers);
+        Ok(APIResult {
+            url,
+            response: page,
+        })
+    }
+}
+
+impl Filterable for SelectBuilder {
+    fn filters_mut(&mut self) -> &mut Vec<Filter> {
+        &mut self.filters
+    }
+}
+
+/// PostgREST accepts large bodies, but smaller batches keep a failure from
+/// costing the whole import.
+const MAX_BATCH_ROWS: usize = 500;
+const MAX_BATCH_BYTES: usize = 1024 * 1024;
+
+/// A batch of an insert that failed, and which rows it held.
+#[derive(Debug)]
+pub struct BatchFailure {
+    pub batch: usize,
+    pub rows: Range<usize>,
+    pub error: Error,
+}
+
+/// The outcome of inserting every batch, successful or not.
+#[derive(Debug)]
+pub struct BatchReport<J> {
+    pub inserted: Vec<J>,
+    pub failures: Vec<BatchFailure>,
+}
+
+/// A JSON array of consecutive rows, ready to send.
+struct Batch {
+    rows: Range<usize>,
+    body: Vec<u8>,
+
+    /// Every key used by a row in the batch, for `missing=default`.
+    columns: Vec<String>,
+}
+
+/// Serializes rows into batches on demand, so only the batch being sent
+/// is held as JSON.
+struct Batches<'a, J> {
+    rows: &'a [J],
+    next: usize,
+
+    /// Index of the next batch.
+    batch: usize,
+
+    /// A serialized row that didn't fit in the previous batch.
+    pending: Option<Vec<u8>>,
+    max_rows: usize,
+    max_bytes: usize,
+    missing_default: bool,
+}
+
+impl<J> Batches<'_, J>
+where
+    J: Serialize,
+{
+    fn serialized(&mut self, i: usize) -> Result<Vec<u8>> {
+        match self.pending.take() {
+            Some(row) => Ok(row),
+            None => Ok(serde_json::to_vec(&self.rows[i])?),
+        }
+    }
+
+    /// Fails the batch started at row `start` on the current row, which is
+    /// skipped so later batches can still be sent.
+    fn fail(&mut self, start: usize, error: Error) -> BatchFailure {
+        self.next += 1;
+        BatchFailure {
+            batch: self.batch - 1,
+            rows: start..self.next,
+            error,
+        }
+    }
+
+    fn next_batch(&mut self) -> std::result::Result<Batch, BatchFailure> {
+        let start = self.next;
+        self.batch += 1;
+        let mut body = vec![b'['];
+        let mut columns: Vec<String> = Vec::new();
+        while self.next < self.rows.len() {
+            let row = match self.serialized(self.next) {
+                Ok(row) => row,
+                Err(error) => return Err(self.fail(start, error)),
+            };
+            let count = self.next - start;
+            // The first row always goes in, however large
+            if count > 0 && (count >= self.max_rows || body.len() + row.len() + 1 > self.max_bytes)
+            {
+                self.pending = Some(row);
+                break;
+            }
+            if self.missing_default {
+                let keys: BTreeMap<String, IgnoredAny> = match serde_json::from_slice(&row) {
+                    Ok(keys) => keys,
+                    Err(error) => return Err(self.fail(start, error.into())),
+                };
+                for key in keys.into_keys() {
+                    if !columns.contains(&key) {
+                        columns.push(key);
+                    }
+                }
+            }
+            if count > 0 {
+                body.push(b',');
+            }
+            body.extend_from_slice(&row);
+            self.next += 1;
+        }
+        body.push(b']');
+        Ok(Batch {
+            rows: start..self.next,
+            body,
+            columns,
+        })
+    }
+}
+
+impl<J> Iterator for Batches<'_, J>
+where
+    J: Serialize,
+{
+    type Item = std::result::Result<Batch, BatchFailure>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.rows.len() {
+            return None;
+        }
+        Some(self.next_batch())
+    }
 }
 
 pub struct InsertBuilder<J>
@@ -129,6 +870,9 @@ where
 {
     table: SupabaseTable,
     rows: Vec<J>,
+    batch_rows: usize,
+    batch_bytes: usize,
+    missing_default: bool,
 }
 
 impl<J> InsertBuilder<J>
@@ -136,60 +880,280 @@ where
     J: Serialize + DeserializeOwned,
 {
     pub fn new(table: SupabaseTable, rows: Vec<J>) -> Self {
-        Self { table, rows }
+        Self {
+            table,
+            rows,
+            batch_rows: MAX_BATCH_ROWS,
+            batch_bytes: MAX_BATCH_BYTES,
+            missing_default: false,
+        }
     }
 
-    pub async fn request(&self) -> Result<Vec<J>> {
-        let url = self.table.table_url();
+    /// At most `rows` rows per request.
+    #[allow(dead_code)]
+    pub fn batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// At most `bytes` of JSON per request, unless a single row is larger.
+    #[allow(dead_code)]
+    pub fn batch_bytes(mut self, bytes: usize) -> Self {
+        self.batch_bytes = bytes;
+        self
+    }
+
+    /// Fills keys a row leaves out with the column default rather than
+    /// `null`, so rows with different keys can share a request.
+    #[allow(dead_code)]
+    pub fn missing_default(mut self) -> Self {
+        self.missing_default = true;
+        self
+    }
+
+    fn batches(&self) -> Batches<'_, J> {
+        Batches {
+            rows: &self.rows,
+            next: 0,
+            batch: 0,
+            pending: None,
+            max_rows: self.batch_rows,
+            max_bytes: self.batch_bytes,
+            missing_default: self.missing_default,
+        }
+    }
+
+    async fn request_batch(&self, batch: Batch) -> Result<Vec<J>> {
+        let mut url = self.table.table_url();
+        let mut prefer: &[&str] = &[];
+        if self.missing_default {
+            // `missing=default` only applies to the listed columns
+            url.push_str(&format!("?columns={}", encode(&batch.columns.join(","))));
+            prefer = &["missing=default"];
+        }
         Ok(
-            supabase_request_builder(RequestMethod::POST, &url, &self.table.client.key)?
-                .json(&self.rows)
+            supabase_request_builder(RequestMethod::POST, &url, &self.table.client.key, prefer)?
+                .body(batch.body)
                 .request_model::<Vec<J>>()
                 .await?
                 .response,
         )
     }
+
+    /// Inserts every batch in order, stopping at the first that fails.
+    /// Earlier batches stay inserted.
+    pub async fn request(&self) -> Result<Vec<J>> {
+        let mut inserted = Vec::new();
+        for batch in self.batches() {
+            let (rows, result) = match batch {
+                Ok(batch) => (batch.rows.clone(), self.request_batch(batch).await),
+                Err(failure) => (failure.rows, Err(failure.error)),
+            };
+            match result {
+                Ok(batch) => inserted.extend(batch),
+                Err(err) => {
+                    if rows.start > 0 {
+                        warn!(
+                            "Insert into '{}' failed at rows {:?}, {} rows already inserted",
+                            self.table.table_name,
+                            rows,
+                            inserted.len()
+                        );
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// Inserts every batch, carrying on past failures and reporting them.
+    #[allow(dead_code)]
+    pub async fn request_batches(&self) -> BatchReport<J> {
+        let mut report = BatchReport {
+            inserted: Vec::new(),
+            failures: Vec::new(),
+        };
+        for (index, batch) in self.batches().enumerate() {
+            let result = match batch {
+                Ok(batch) => {
+                    let rows = batch.rows.clone();
+                    self.request_batch(batch)
+                        .await
+                        .map_err(|error| BatchFailure {
+                            batch: index,
+                            rows,
+                            error,
+                        })
+                }
+                Err(failure) => Err(failure),
+            };
+            match result {
+                Ok(inserted) => report.inserted.extend(inserted),
+                Err(failure) => {
+                    warn!(
+                        "Batch {} of insert into '{}' failed: {}",
+                        failure.batch, self.table.table_name, failure.error
+                    );
+                    report.failures.push(failure);
+                }
+            }
+        }
+        report
+    }
 }
 
 pub struct DeleteBuilder {
     table: SupabaseTable,
-    notin_column: Option<&'static str>,
-    notin_values: Option<Vec<String>>,
+    filters: Vec<Filter>,
 }
 
 impl DeleteBuilder {
     pub fn new(table: SupabaseTable) -> Self {
         Self {
             table,
-            notin_column: None,
-            notin_values: None,
+            filters: Vec::new(),
         }
     }
 
-    pub fn notin<I>(mut self, column: &'static str, values: I) -> Self
+    pub async fn request<T>(&self) -> Result<Vec<T>>
     where
-        I: IntoIterator<Item = String>,
+        T: DeserializeOwned,
     {
-        let notin_values: Vec<String> = values.into_iter().collect();
-        self.notin_values = Some(notin_values);
-        self.notin_column = Some(column);
-        self
+        let mut url = self.table.table_url();
+        let params = query_params(&self.filters, &[]);
+        if !params.is_empty() {
+            url.push_str(&format!("?{}", params.join("&")));
+        }
+        Ok(
+            supabase_request_builder(RequestMethod::DELETE, &url, &self.table.client.key, &[])?
+                .request_model::<Vec<T>>()
+                .await?
+                .response,
+        )
+    }
+}
+
+impl Filterable for DeleteBuilder {
+    fn filters_mut(&mut self) -> &mut Vec<Filter> {
+        &mut self.filters
+    }
+}
+
+pub struct UpdateBuilder<J>
+where
+    J: Serialize,
+{
+    table: SupabaseTable,
+    values: J,
+    filters: Vec<Filter>,
+}
+
+#[allow(dead_code)]
+impl<J> UpdateBuilder<J>
+where
+    J: Serialize,
+{
+    pub fn new(table: SupabaseTable, values: J) -> Self {
+        Self {
+            table,
+            values,
+            filters: Vec::new(),
+        }
     }
 
     pub async fn request<T>(&self) -> Result<Vec<T>>
     where
         T: DeserializeOwned,
     {
-        let mut url = self.table.table_url();
-
-        if let (Some(column), Some(values)) = (self.notin_column, &self.notin_values) {
-            url.push_str(&format!("?{}=not.in.({})", column, values.join(",")));
+        // An unfiltered PATCH would rewrite the whole table
+        if self.filters.is_empty() {
+            return Err(Error::Custom(format!(
+                "Refusing to update every row of '{}'",
+                self.table.table_name
+            )));
         }
+        let url = format!(
+            "{}?{}",
+            self.table.table_url(),
+            query_params(&self.filters, &[]).join("&")
+        );
         Ok(
-            supabase_request_builder(RequestMethod::DELETE, &url, &self.table.client.key)?
+            supabase_request_builder(RequestMethod::PATCH, &url, &self.table.client.key, &[])?
+                .json(&self.values)
                 .request_model::<Vec<T>>()
                 .await?
                 .response,
         )
     }
 }
+
+impl<J> Filterable for UpdateBuilder<J>
+where
+    J: Serialize,
+{
+    fn filters_mut(&mut self) -> &mut Vec<Filter> {
+        &mut self.filters
+    }
+}
+
+pub struct UpsertBuilder<J>
+where
+    J: Serialize + DeserializeOwned,
+{
+    table: SupabaseTable,
+    rows: Vec<J>,
+    on_conflict: Vec<String>,
+    ignore_duplicates: bool,
+}
+
+impl<J> UpsertBuilder<J>
+where
+    J: Serialize + DeserializeOwned,
+{
+    pub fn new(table: SupabaseTable, rows: Vec<J>) -> Self {
+        Self {
+            table,
+            rows,
+            on_conflict: Vec::new(),
+            ignore_duplicates: false,
+        }
+    }
+
+    /// Keeps the existing row on conflict instead of merging into it.
+    pub fn ignore_duplicates(mut self) -> Self {
+        self.ignore_duplicates = true;
+        self
+    }
+
+    /// Columns with a unique constraint that identify a duplicate. Defaults
+    /// to the primary key.
+    pub fn on_conflict(mut self, columns: &[&str]) -> Self {
+        self.on_conflict = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    pub async fn request(&self) -> Result<Vec<J>> {
+        let mut url = self.table.table_url();
+        if !self.on_conflict.is_empty() {
+            url.push_str(&format!(
+                "?on_conflict={}",
+                encode(&self.on_conflict.join(","))
+            ));
+        }
+        Ok(supabase_request_builder(
+            RequestMethod::POST,
+            &url,
+            &self.table.client.key,
+            &[if self.ignore_duplicates {
+                "resolution=ignore-duplicates"
+            } else {
+                "resolution=merge-duplicates"
+            }],
+        )?
+        .json(&self.rows)
+        .request_model::<Vec<J>>()
+        .await?
+        .response)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    providers::{MusicProvider, Track},
+    Error, Result,
+};
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use log::{info, warn};
+use redis::{AsyncCommands, Client};
+use serde::Deserialize;
+use std::io::Cursor;
+
+/// Square sizes, in pixels, the cover proxy serves.
+pub const COVER_SIZES: [u32; 4] = [64, 128, 256, 512];
+const DEFAULT_COVER_SIZE: u32 = 512;
+
+/// Resized covers never change, so they can stay cached for a long time.
+const COVER_EXPIRATION: usize = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl CoverFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoverQuery {
+    pub size: Option<u32>,
+    pub format: Option<CoverFormat>,
+}
+
+impl CoverQuery {
+    pub fn size(&self) -> u32 {
+        self.size.unwrap_or(DEFAULT_COVER_SIZE)
+    }
+
+    pub fn format(&self) -> CoverFormat {
+        self.format.unwrap_or_default()
+    }
+}
+
+/// What is needed to fetch a stored track's cover, selected on its own so
+/// the proxy never loads previews.
+#[derive(Debug, Deserialize)]
+pub struct CoverSource {
+    pub id: u64,
+
+    /// Missing on assets stored before providers existed.
+    pub provider: Option<String>,
+
+    #[serde(rename = "coverUrl")]
+    pub cover_url: String,
+}
+
+impl CoverSource {
+    pub fn provider(&self) -> &str {
+        self.provider.as_deref().unwrap_or("deezer")
+    }
+
+    fn track(&self) -> Track {
+        Track {
+            id: self.id,
+            title: String::new(),
+            full_title: String::new(),
+            artist: String::new(),
+            album_title: String::new(),
+            cover_url: self.cover_url.clone(),
+            preview_url: String::new(),
+        }
+    }
+}
+
+/// Crops a cover to a square of `size` pixels and re-encodes it.
+pub fn resize_cover(bytes: &[u8], size: u32, format: CoverFormat) -> Result<Vec<u8>> {
+    let cover = image::load_from_memory(bytes)?.resize_to_fill(size, size, FilterType::Lanczos3);
+    let mut encoded = Vec::new();
+    let mut writer = Cursor::new(&mut encoded);
+    match format {
+        // JPEG has no alpha channel
+        CoverFormat::Jpeg => {
+            DynamicImage::ImageRgb8(cover.to_rgb8()).write_to(&mut writer, ImageFormat::Jpeg)?
+        }
+        CoverFormat::Webp => {
+            DynamicImage::ImageRgba8(cover.to_rgba8()).write_to(&mut writer, ImageFormat::WebP)?
+        }
+    }
+    Ok(encoded)
+}
+
+/// A blurhash of the cover, small enough to ship with the asset and
+/// render as a placeholder while the real cover loads.
+pub fn blurhash(image: &DynamicImage) -> Result<String> {
+    let thumbnail = image.thumbnail(32, 32).to_rgba8();
+    blurhash::encode(
+        4,
+        3,
+        thumbnail.width(),
+        thumbnail.height(),
+        thumbnail.as_raw(),
+    )
+    .map_err(Error::custom)
+}
+
+/// Serves a track's cover at `size` in `format`, from Redis when it has
+/// been requested before, so clients never load art from the provider.
+pub async fn proxied_cover(
+    client: &Client,
+    provider: &dyn MusicProvider,
+    source: &CoverSource,
+    size: u32,
+    format: CoverFormat,
+) -> Result<Vec<u8>> {
+    let key = format!(
+        "cover:{}:{}:{}.{}",
+        source.provider(),
+        source.id,
+        size,
+        format.extension()
+    );
+    let mut conn = client.get_multiplexed_tokio_connection().await?;
+    if let Some(cover) = conn.get::<_, Option<Vec<u8>>>(&key).await? {
+        return Ok(cover);
+    }
+
+    warn!("Cache miss for key: {}", key);
+    let original = provider.cover(&source.track()).await?;
+    let cover = tokio::task::spawn_blocking(move || resize_cover(&original, size, format))
+        .await
+        .map_err(|e| Error::CriticalError(e.to_string()))??;
+    let _: () = conn
+        .set_ex(&key, cover.as_slice(), COVER_EXPIRATION)
+        .await?;
+    info!("Cached cover {}", key);
+    Ok(cover)
+}
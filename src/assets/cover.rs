@@ -0,0 +1,282 @@
+use super::manager::select_mashed_cover;
+use super::models::MashedCover;
+use super::palette::{self, Palette};
+use super::track::SeededRng;
+use crate::{config, Error, Result};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use base64::{engine::general_purpose, Engine as _};
+use bytes::Bytes;
+use image::{imageops::FilterType, DynamicImage, ImageFormat, Rgba, RgbaImage};
+use log::{info, warn};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{io::Cursor, sync::OnceLock};
+
+const COVER_SIZE: u32 = 600;
+const TITLE_BAND: f32 = 0.18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MaskShape {
+    Circle,
+    Diamond,
+}
+
+/// How the two album covers are combined. Everything that affects the
+/// output is recorded here, so a stored cover can be re-rendered exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CoverLayout {
+    /// Split along the diagonal from the top left, or the top right if
+    /// `flipped`.
+    DiagonalSplit { flipped: bool },
+
+    /// A checkerboard of `tiles` x `tiles` squares.
+    Tiles { tiles: u32 },
+
+    /// A left-to-right cross-fade.
+    Blend,
+
+    /// The first cover cut out in `shape` over the second.
+    Mask { shape: MaskShape },
+}
+
+impl CoverLayout {
+    pub fn random(rng: &mut SeededRng) -> Self {
+        match rng.gen_range(0..4) {
+            0 => Self::DiagonalSplit { flipped: rng.gen() },
+            1 => Self::Tiles {
+                tiles: *[2, 3, 4].choose(rng).unwrap_or(&2),
+            },
+            2 => Self::Blend,
+            _ => Self::Mask {
+                shape: if rng.gen() {
+                    MaskShape::Circle
+                } else {
+                    MaskShape::Diamond
+                },
+            },
+        }
+    }
+
+    /// How much of the first cover shows at (x, y), from 0 to 1.
+    fn weight(&self, x: u32, y: u32) -> f32 {
+        let size = COVER_SIZE as f32;
+        let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+        match *self {
+            Self::DiagonalSplit { flipped } => {
+                let x = if flipped { size - fx } else { fx };
+                // A couple of pixels of feathering to avoid jagged edges
+                ((size - x - fy) / 2.0 + 0.5).clamp(0.0, 1.0)
+            }
+            Self::Tiles { tiles } => {
+                let tile = COVER_SIZE / tiles.max(1);
+                if (x / tile + y / tile).is_multiple_of(2) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Blend => 1.0 - fx / size,
+            Self::Mask { shape } => {
+                let (dx, dy) = ((fx - size / 2.0).abs(), (fy - size / 2.0).abs());
+                let radius = size * 0.35;
+                let distance = match shape {
+                    MaskShape::Circle => (dx * dx + dy * dy).sqrt(),
+                    MaskShape::Diamond => dx + dy,
+                };
+                (radius - distance + 0.5).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+fn blend(a: Rgba<u8>, b: Rgba<u8>, weight: f32) -> Rgba<u8> {
+    Rgba(std::array::from_fn(|i| {
+        (a.0[i] as f32 * weight + b.0[i] as f32 * (1.0 - weight)).round() as u8
+    }))
+}
+
+fn load_cover(bytes: &[u8]) -> Result<RgbaImage> {
+    let cover = image::load_from_memory(bytes)?;
+    Ok(cover
+        .resize_to_fill(COVER_SIZE, COVER_SIZE, FilterType::Lanczos3)
+        .to_rgba8())
+}
+
+/// Renders `title` in a dark band along the bottom, shrinking it to fit.
+fn draw_title(canvas: &mut RgbaImage, font: &FontVec, title: &str) {
+    let band_height = (COVER_SIZE as f32 * TITLE_BAND) as u32;
+    let band_top = COVER_SIZE - band_height;
+    for y in band_top..COVER_SIZE {
+        for x in 0..COVER_SIZE {
+            let pixel = canvas.get_pixel_mut(x, y);
+            *pixel = blend(Rgba([0, 0, 0, 255]), *pixel, 0.6);
+        }
+    }
+
+    let margin = COVER_SIZE as f32 * 0.05;
+    let max_width = COVER_SIZE as f32 - 2.0 * margin;
+    let mut scale = PxScale::from(band_height as f32 * 0.5);
+    let width = text_width(font, scale, title);
+    if width > max_width {
+        scale = PxScale::from(scale.y * max_width / width);
+    }
+
+    let scaled = font.as_scaled(scale);
+    let width = text_width(font, scale, title);
+    let mut caret = (COVER_SIZE as f32 - width) / 2.0;
+    let baseline =
+        band_top as f32 + (band_height as f32 + scaled.ascent() + scaled.descent()) / 2.0;
+    let mut previous = None;
+    for c in title.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(scale, ab_glyph::point(caret, baseline));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|x, y, coverage| {
+            let (x, y) = (
+                bounds.min.x as i32 + x as i32,
+                bounds.min.y as i32 + y as i32,
+            );
+            if (0..COVER_SIZE as i32).contains(&x) && (0..COVER_SIZE as i32).contains(&y) {
+                let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+                *pixel = blend(Rgba([255, 255, 255, 255]), *pixel, coverage);
+            }
+        });
+    }
+}
+
+fn text_width(font: &FontVec, scale: PxScale, text: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut previous = None;
+    let mut width = 0.0;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Composites two album covers into a square image.
+pub fn composite(
+    cover1: &[u8],
+    cover2: &[u8],
+    layout: CoverLayout,
+    title: Option<(&FontVec, &str)>,
+) -> Result<DynamicImage> {
+    let (cover1, cover2) = (load_cover(cover1)?, load_cover(cover2)?);
+    let mut canvas = RgbaImage::from_fn(COVER_SIZE, COVER_SIZE, |x, y| {
+        blend(
+            *cover1.get_pixel(x, y),
+            *cover2.get_pixel(x, y),
+            layout.weight(x, y),
+        )
+    });
+    if let Some((font, title)) = title {
+        draw_title(&mut canvas, font, title);
+    }
+
+    Ok(DynamicImage::ImageRgb8(
+        DynamicImage::ImageRgba8(canvas).to_rgb8(),
+    ))
+}
+
+/// Encodes a composited cover as JPEG, extracting its palette on the way
+/// since the image is already decoded.
+fn encode_cover(image: DynamicImage) -> Result<(Vec<u8>, Option<Palette>)> {
+    let palette = palette::extract(&image)
+        .inspect_err(|err| warn!("Failed to extract mashed cover palette: {}", err))
+        .ok();
+    let mut jpeg = Vec::new();
+    image.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)?;
+    Ok((jpeg, palette))
+}
+
+fn load_font(path: &str) -> Result<FontVec> {
+    FontVec::try_from_vec(std::fs::read(path)?)
+        .map_err(|e| Error::Custom(format!("Invalid font '{path}': {e}")))
+}
+
+/// The configured title font, read once. A missing or invalid font only
+/// costs covers their title.
+fn cover_font() -> Result<Option<&'static FontVec>> {
+    static FONT: OnceLock<Option<FontVec>> = OnceLock::new();
+    let path = config::get()?.cover_font_file.as_deref();
+    Ok(FONT
+        .get_or_init(|| {
+            let path = path?;
+            load_font(path)
+                .inspect_err(|err| warn!("Covers will be untitled: {}", err))
+                .ok()
+        })
+        .as_ref())
+}
+
+/// Composites both tracks' covers with a random layout, writing the mashed
+/// title on top when a font is configured. Missing artwork only costs the
+/// mashup its cover, so failures are logged and yield `None`.
+pub async fn mash_covers(
+    cover1: Option<Bytes>,
+    cover2: Option<Bytes>,
+    title: &str,
+    rng: &mut SeededRng,
+) -> Result<Option<MashedCover>> {
+    let layout = CoverLayout::random(rng);
+    let (Some(cover1), Some(cover2)) = (cover1, cover2) else {
+        warn!("Skipping mashed cover: a source cover is missing");
+        return Ok(None);
+    };
+
+    let font = cover_font()?;
+    let title = title.to_string();
+    let titled = font.is_some();
+    let image = tokio::task::spawn_blocking(move || {
+        composite(
+            &cover1,
+            &cover2,
+            layout,
+            font.map(|font| (font, title.as_str())),
+        )
+        .and_then(encode_cover)
+    })
+    .await
+    .map_err(|e| Error::CriticalError(e.to_string()))?;
+
+    match image {
+        Ok((image, palette)) => Ok(Some(MashedCover {
+            layout,
+            titled,
+            palette,
+            image: general_purpose::STANDARD.encode(image),
+        })),
+        Err(err) => {
+            warn!("Failed to composite mashed cover: {}", err);
+            Ok(None)
+        }
+    }
+}
+
+/// The stored mashed cover of mashup `id`, as JPEG bytes.
+pub async fn stored_cover(id: i64) -> Result<Option<Vec<u8>>> {
+    let Some(cover) = select_mashed_cover(id).await? else {
+        return Ok(None);
+    };
+    info!("Decoding cover of mashup {}", id);
+    general_purpose::STANDARD
+        .decode(cover.image)
+        .map(Some)
+        .map_err(Error::custom)
+}
@@ -0,0 +1,175 @@
+use super::manager::generate_mashup;
+use super::mash::FieldMashers;
+use super::models::{MashedTrackAsset, MashupAssets, MashupAssetsInsert};
+use super::normalize::matches_title;
+use super::track::Sources;
+use super::words;
+use crate::{
+    apis::supabase::{self as sb, Filterable},
+    config,
+    dictionaries::Dictionary,
+    providers::MusicProvider,
+    Error, Result,
+};
+use chrono::{NaiveDate, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+/// A daily mashup with the answers hidden.
+#[derive(Debug, Serialize)]
+pub struct DailyPuzzle {
+    #[serde(rename = "puzzleNumber")]
+    pub puzzle_number: i64,
+    pub date: String,
+
+    #[serde(rename = "mashedTrack")]
+    pub mashed_track: MashedTrackAsset,
+    pub previews: [String; 2],
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DailyGuess {
+    pub track1: String,
+    pub track2: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyResult {
+    pub solved: bool,
+
+    /// The full mashup, only revealed once solved.
+    pub answers: Option<MashupAssets>,
+}
+
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+fn puzzle_number(date: NaiveDate) -> Result<i64> {
+    Ok((date - config::get()?.daily_epoch).num_days() + 1)
+}
+
+/// Derives the seed for `date` from the server secret, so every user gets the
+/// same mashup but it can't be generated ahead of time without the secret.
+fn daily_seed(date: NaiveDate) -> Result<u64> {
+    let secret = config::get()?
+        .daily_secret
+        .as_ref()
+        .ok_or(Error::Custom("DAILY_SECRET is not set".into()))?;
+    let digest = Sha256::digest(format!("{secret}:{date}"));
+    let mut seed = [0u8; 8];
+    seed.copy_from_slice(&digest[..8]);
+    Ok(u64::from_be_bytes(seed))
+}
+
+async fn select_daily_assets(date: NaiveDate) -> Result<Option<MashupAssets>> {
+    let mut assets = sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .select()
+        .eq("dailyDate", date)
+        .order("createdAt", sb::OrderDirection::ASC)
+        .limit(1)
+        .request::<MashupAssets>()
+        .await?;
+    Ok(assets.pop())
+}
+
+/// Stores a generated daily unless one was stored for its date meanwhile,
+/// e.g. by a concurrent first request. Relies on a unique index on
+/// `dailyDate`.
+async fn insert_daily_row(row: MashupAssetsInsert) -> Result<()> {
+    info!("Inserting daily mashup (seed {})", &row.seed);
+    sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .upsert(vec![row])
+        .on_conflict(&["dailyDate"])
+        .ignore_duplicates()
+        .request()
+        .await?;
+    Ok(())
+}
+
+/// Held while a daily is generated.
+static GENERATING: Mutex<()> = Mutex::const_new(());
+
+/// Retrieves the daily mashup for `date`, generating today's on first
+/// request. Other dates without a stored mashup have none.
+async fn daily_assets(
+    provider: &dyn MusicProvider,
+    dictionary: &dyn Dictionary,
+    date: NaiveDate,
+) -> Result<Option<MashupAssets>> {
+    if let Some(assets) = select_daily_assets(date).await? {
+        return Ok(Some(assets));
+    }
+    if date != today() {
+        info!("No daily mashup for {}", date);
+        return Ok(None);
+    }
+
+    // Only today's daily is ever generated, so one lock makes concurrent
+    // first requests wait for a single generation instead of each running it
+    let _generating = GENERATING.lock().await;
+    if let Some(assets) = select_daily_assets(date).await? {
+        return Ok(Some(assets));
+    }
+
+    info!("Generating daily mashup for {}", date);
+    let config = config::get()?;
+    let words = words::from_spec(&config.word_source, config.language, provider)?;
+    let seed = daily_seed(date)?;
+    let sources = Sources {
+        provider,
+        dictionary,
+        words: words.as_ref(),
+        language: config.language,
+    };
+    let row = generate_mashup(
+        &sources,
+        &FieldMashers::from_specs(&config.name_mashers, &config.artist_mashers, config)?,
+        seed,
+        None,
+        Some(date.to_string()),
+    )
+    .await?;
+    insert_daily_row(row).await?;
+    match select_daily_assets(date).await? {
+        Some(assets) => Ok(Some(assets)),
+        None => Err(Error::CriticalError("Daily mashup was not stored".into())),
+    }
+}
+
+pub async fn daily_puzzle(
+    provider: &dyn MusicProvider,
+    dictionary: &dyn Dictionary,
+    date: NaiveDate,
+) -> Result<Option<DailyPuzzle>> {
+    let Some(assets) = daily_assets(provider, dictionary, date).await? else {
+        return Ok(None);
+    };
+    Ok(Some(DailyPuzzle {
+        puzzle_number: puzzle_number(date)?,
+        date: date.to_string(),
+        mashed_track: assets.mashed_track,
+        previews: [assets.track1.preview, assets.track2.preview],
+    }))
+}
+
+pub async fn guess_daily(
+    provider: &dyn MusicProvider,
+    dictionary: &dyn Dictionary,
+    date: NaiveDate,
+    guess: &DailyGuess,
+) -> Result<Option<DailyResult>> {
+    let Some(assets) = daily_assets(provider, dictionary, date).await? else {
+        return Ok(None);
+    };
+    let solved = matches_title(&guess.track1, &assets.track1.full_title)
+        && matches_title(&guess.track2, &assets.track2.full_title);
+    Ok(Some(DailyResult {
+        solved,
+        answers: solved.then_some(assets),
+    }))
+}
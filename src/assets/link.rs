@@ -0,0 +1,205 @@
+use super::models::{TrackAsset, WordLink, WordRelation};
+use super::track::{build_track_asset_from_search, BuiltTrack, SeededRng, Sources, TrackSearch};
+use crate::{apis::dictionary::Word, Result};
+use log::{debug, info, warn};
+use rand::seq::SliceRandom;
+use random_word::all;
+use serde::{Deserialize, Serialize};
+
+const MAX_CANDIDATES: usize = 10;
+
+/// How the second seed word should relate to the first. `Any` tries every
+/// relation in a random order.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkMode {
+    Any,
+    Synonym,
+    Antonym,
+    Etymology,
+    Rhyme,
+    PartOfSpeech,
+}
+
+impl LinkMode {
+    fn relations(self, rng: &mut SeededRng) -> Vec<WordRelation> {
+        match self {
+            Self::Any => {
+                let mut relations = vec![
+                    WordRelation::Synonym,
+                    WordRelation::Antonym,
+                    WordRelation::Etymology,
+                    WordRelation::Rhyme,
+                    WordRelation::PartOfSpeech,
+                ];
+                relations.shuffle(rng);
+                relations
+            }
+            Self::Synonym => vec![WordRelation::Synonym],
+            Self::Antonym => vec![WordRelation::Antonym],
+            Self::Etymology => vec![WordRelation::Etymology],
+            Self::Rhyme => vec![WordRelation::Rhyme],
+            Self::PartOfSpeech => vec![WordRelation::PartOfSpeech],
+        }
+    }
+}
+
+fn is_candidate(word: &str, seed: &str) -> bool {
+    !word.eq_ignore_ascii_case(seed) && !word.is_empty() && word.chars().all(char::is_alphabetic)
+}
+
+fn related_words(entry: &Word, antonyms: bool) -> Vec<String> {
+    let mut related = Vec::new();
+    for meaning in entry.meanings.iter().flatten() {
+        let words = if antonyms {
+            &meaning.antonyms
+        } else {
+            &meaning.synonyms
+        };
+        related.extend(words.iter().cloned());
+        for definition in &meaning.definitions {
+            let words = if antonyms {
+                &definition.antonyms
+            } else {
+                &definition.synonyms
+            };
+            related.extend(words.iter().cloned());
+        }
+    }
+    related
+}
+
+/// The part of a word a rhyme must share: its last vowel group onwards.
+fn rhyme_key(word: &str) -> Option<String> {
+    let chars: Vec<char> = word.to_lowercase().chars().collect();
+    let is_vowel = |c: &char| "aeiouy".contains(*c);
+    let last_vowel = chars.iter().rposition(is_vowel)?;
+    let start = chars[..last_vowel]
+        .iter()
+        .rposition(|c| !is_vowel(c))
+        .map_or(0, |i| i + 1);
+    (start > 0).then(|| chars[start..].iter().collect())
+}
+
+/// Words quoted in the etymology (e.g. `from Old English 'heal'`) plus words
+/// sharing the seed's first five letters, which usually share its root.
+fn etymology_words(entry: &Word, sources: &Sources<'_>) -> Vec<String> {
+    let mut words: Vec<String> = entry
+        .origin
+        .iter()
+        .flat_map(|origin| {
+            origin
+                .split(['\'', '"', '‘', '’', '“', '”'])
+                .skip(1)
+                .step_by(2)
+        })
+        .map(|w| w.to_lowercase())
+        .collect();
+    let seed = entry.word.to_lowercase();
+    if let Some(root) = seed.get(..5) {
+        words.extend(
+            all(sources.language.word_list())
+                .iter()
+                .filter(|w| w.starts_with(root))
+                .map(|w| w.to_string()),
+        );
+    }
+    words
+}
+
+async fn same_part_of_speech(
+    entry: &Word,
+    sources: &Sources<'_>,
+    rng: &mut SeededRng,
+) -> Vec<String> {
+    let Some(part_of_speech) = entry
+        .meanings
+        .as_ref()
+        .and_then(|m| m.first())
+        .map(|m| m.part_of_speech.clone())
+    else {
+        return Vec::new();
+    };
+    let mut words = Vec::new();
+    for _ in 0..MAX_CANDIDATES {
+        let Some(word) = sources.words.word(rng) else {
+            break;
+        };
+        let Ok(Some(candidate)) = sources.dictionary.lookup(&word, sources.language).await else {
+            continue;
+        };
+        let matches = candidate
+            .meanings
+            .as_ref()
+            .and_then(|m| m.first())
+            .is_some_and(|m| m.part_of_speech == part_of_speech);
+        if matches {
+            words.push(word);
+        }
+    }
+    words
+}
+
+async fn candidates(
+    relation: WordRelation,
+    entry: &Word,
+    sources: &Sources<'_>,
+    rng: &mut SeededRng,
+) -> Vec<String> {
+    let mut words = match relation {
+        WordRelation::Synonym => related_words(entry, false),
+        WordRelation::Antonym => related_words(entry, true),
+        WordRelation::Etymology => etymology_words(entry, sources),
+        WordRelation::Rhyme => match rhyme_key(&entry.word) {
+            Some(key) => all(sources.language.word_list())
+                .iter()
+                .filter(|w| w.ends_with(&key) && rhyme_key(w).as_ref() == Some(&key))
+                .map(|w| w.to_string())
+                .collect(),
+            None => Vec::new(),
+        },
+        WordRelation::PartOfSpeech => same_part_of_speech(entry, sources, rng).await,
+    };
+    words.retain(|w| is_candidate(w, &entry.word));
+    words.sort();
+    words.dedup();
+    words.shuffle(rng);
+    words.truncate(MAX_CANDIDATES);
+    words
+}
+
+/// Builds a second track whose seed word is related to `track1`'s, returning
+/// `None` if no related word turns up any tracks.
+pub async fn build_linked_track_asset(
+    sources: &Sources<'_>,
+    track1: &TrackAsset,
+    mode: LinkMode,
+    rng: &mut SeededRng,
+) -> Result<Option<(BuiltTrack, WordLink)>> {
+    let entry = &track1.origin.word;
+    for relation in mode.relations(rng) {
+        for word in candidates(relation, entry, sources, rng).await {
+            debug!("Querying linked word '{}' ({:?})", word, relation);
+            let total = match sources.provider.search(&word).await {
+                Ok(total) => total,
+                Err(err) => {
+                    warn!("Linked search for '{}' failed: {}", word, err);
+                    continue;
+                }
+            };
+            if total == 0 {
+                continue;
+            }
+            info!("Linked '{}' to '{}' by {:?}", entry.word, word, relation);
+            let link = WordLink {
+                relation,
+                from: entry.word.clone(),
+                to: word.clone(),
+            };
+            let search = TrackSearch { word, total };
+            let track = build_track_asset_from_search(sources, search, rng).await?;
+            return Ok(Some((track, link)));
+        }
+    }
+    Ok(None)
+}
@@ -1,51 +1,270 @@
-use super::models::{MashupAssets, MashupAssetsInsert};
-use super::track::{build_track_asset, mash_track_assets};
+use super::artwork::CoverSource;
+use super::cover::mash_covers;
+use super::link::{build_linked_track_asset, LinkMode};
+use super::markov::{self, Markov};
+use super::mash::{mash_track_assets, FieldMashers};
+use super::models::{MashedCover, MashupAssets, MashupAssetsInsert, MashupSummary};
+use super::notes::liner_notes;
+use super::track::{build_track_asset, SeededRng, Sources};
+use super::words;
 use crate::{
-    apis::supabase::{self as sb},
+    apis::supabase::{self as sb, Filterable},
+    config,
+    dictionaries::Dictionary,
+    language::Language,
+    providers::MusicProvider,
     Error, Result,
 };
 use actix_web::web::Data;
 use log::{info, warn};
+use rand::SeedableRng;
 use redis::{aio::MultiplexedConnection, AsyncCommands, Client, FromRedisValue};
+use serde::Deserialize;
 use std::{cmp, sync::Arc};
 
 const TRACK_LIMIT: u8 = 3;
+/// Regular mashups `/history` returns. `refresh_assets` prunes the rest.
+const HISTORY_LIMIT: u64 = 50;
 
-async fn insert_new_asset_row() -> Result<()> {
-    let track1 = build_track_asset().await?;
-    let track2 = build_track_asset().await?;
-    let mashed_track = mash_track_assets(&track1, &track2);
-    info!("Inserting: {}, {}", &track1.title, &track2.title);
+/// Keys of `MashupSummary` and the columns or JSON paths they come from.
+/// Summaries name the source tracks, so they are only ever selected for
+/// regular mashups, never dailies.
+const SUMMARY_COLUMNS: [(&str, &str); 17] = [
+    ("id", "id"),
+    ("createdAt", "createdAt"),
+    ("seed", "seed"),
+    ("dailyDate", "dailyDate"),
+    ("wordLink", "wordLink"),
+    ("title", "mashedTrack->>title"),
+    ("artist", "mashedTrack->>artist"),
+    ("albumTitle", "mashedTrack->>albumTitle"),
+    ("description", "mashedTrack->>description"),
+    ("track1Title", "track1->>title"),
+    ("track1Artist", "track1->>artist"),
+    ("track1CoverUrl", "track1->>coverUrl"),
+    ("track1Blurhash", "track1->>blurhash"),
+    ("track2Title", "track2->>title"),
+    ("track2Artist", "track2->>artist"),
+    ("track2CoverUrl", "track2->>coverUrl"),
+    ("track2Blurhash", "track2->>blurhash"),
+];
+
+/// Per-refresh overrides for how the new mashup is generated.
+#[derive(Debug, Default, Deserialize)]
+pub struct RefreshOptions {
+    pub seed: Option<u64>,
+
+    /// A word source spec, see `words::from_spec`.
+    pub words: Option<String>,
+    pub language: Option<Language>,
+
+    /// Relate the second seed word to the first.
+    pub link: Option<LinkMode>,
+
+    /// Name masher strategies, see `MasherSet::from_spec`.
+    pub mashers: Option<String>,
+
+    #[serde(rename = "artistMashers")]
+    pub artist_mashers: Option<String>,
+}
+
+pub async fn generate_mashup(
+    sources: &Sources<'_>,
+    mashers: &FieldMashers,
+    seed: u64,
+    link: Option<LinkMode>,
+    daily_date: Option<String>,
+) -> Result<MashupAssetsInsert> {
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let built1 = build_track_asset(sources, &mut rng).await?;
+    let track1 = built1.asset;
+    let linked = match link {
+        Some(mode) => build_linked_track_asset(sources, &track1, mode, &mut rng).await?,
+        None => None,
+    };
+    let (built2, word_link) = match linked {
+        Some((built2, word_link)) => (built2, Some(word_link)),
+        None => {
+            if link.is_some() {
+                warn!("No linked word found for '{}'", track1.origin.word.word);
+            }
+            (build_track_asset(sources, &mut rng).await?, None)
+        }
+    };
+    let track2 = built2.asset;
+    let mut mashed_track = mash_track_assets(&track1, &track2, mashers, sources.language, &mut rng);
+    mashed_track.cover =
+        mash_covers(built1.cover, built2.cover, &mashed_track.title, &mut rng).await?;
+    mashed_track.description = liner_notes(
+        &track1,
+        &track2,
+        &mashed_track,
+        word_link.as_ref(),
+        &mut rng,
+    );
+    Ok(MashupAssetsInsert {
+        seed: seed.to_string(),
+        daily_date,
+        word_link,
+        mashers: mashers.specs.clone(),
+        link,
+        track1,
+        track2,
+        mashed_track,
+    })
+}
+
+pub async fn insert_asset_row(row: MashupAssetsInsert) -> Result<()> {
+    info!(
+        "Inserting: {}, {} (seed {})",
+        &row.track1.title, &row.track2.title, &row.seed
+    );
     sb::SupabaseClient::new()?
         .from("mashup_assets")
-        .insert(MashupAssetsInsert {
-            track1,
-            track2,
-            mashed_track,
-        })
+        .insert(row)
         .request()
         .await?;
     Ok(())
 }
 
-async fn select_assets_from_database() -> Result<Vec<MashupAssets>> {
-    let assets = sb::SupabaseClient::new()?
+fn summary_columns() -> sb::Columns {
+    SUMMARY_COLUMNS
+        .iter()
+        .fold(sb::Columns::new(), |columns, (alias, column)| {
+            if alias == column {
+                columns.column(column)
+            } else {
+                columns.alias(alias, column)
+            }
+        })
+}
+
+fn current_assets_select() -> Result<sb::SelectBuilder> {
+    Ok(sb::SupabaseClient::new()?
         .from("mashup_assets")
         .select()
+        .is("dailyDate", sb::IsValue::Null)
         .order("createdAt", sb::OrderDirection::DESC)
-        .limit(TRACK_LIMIT as u64)
-        .request::<MashupAssets>()
-        .await?;
+        .limit(TRACK_LIMIT as u64))
+}
+
+async fn select_assets_from_database() -> Result<Vec<MashupAssets>> {
+    let assets = current_assets_select()?.request::<MashupAssets>().await?;
     info!("Select {} assets", assets.len());
     Ok(assets)
 }
 
+#[derive(Debug, Deserialize)]
+struct MashedCoverRow {
+    cover: Option<MashedCover>,
+}
+
+/// The mashed cover of mashup `id`, without the rest of the row.
+pub async fn select_mashed_cover(id: i64) -> Result<Option<MashedCover>> {
+    let rows = sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .select()
+        .columns(sb::Columns::new().alias("cover", "mashedTrack->cover"))
+        .eq("id", id)
+        .limit(1)
+        .request::<MashedCoverRow>()
+        .await?;
+    Ok(rows.into_iter().next().and_then(|row| row.cover))
+}
+
+/// Where to fetch the cover of `track` (1 or 2) of mashup `id` from.
+/// Dailies have none, since their covers would give the answers away.
+pub async fn select_cover_source(id: i64, track: u8) -> Result<Option<CoverSource>> {
+    let column = |path: &str| format!("track{track}{path}");
+    let sources = sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .select()
+        .columns(
+            sb::Columns::new()
+                .alias("id", &column("->id"))
+                .alias("provider", &column("->>provider"))
+                .alias("coverUrl", &column("->>coverUrl")),
+        )
+        .eq("id", id)
+        .is("dailyDate", sb::IsValue::Null)
+        .limit(1)
+        .request::<CoverSource>()
+        .await?;
+    Ok(sources.into_iter().next())
+}
+
+#[derive(Debug, Deserialize)]
+struct MashupId {
+    id: i64,
+}
+
+/// Asks for `MashupSummary`s instead of whole mashups.
+#[derive(Debug, Deserialize)]
+pub struct SummaryQuery {
+    #[serde(default)]
+    pub summary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub language: Option<Language>,
+
+    #[serde(default)]
+    pub summary: bool,
+}
+
+/// Past regular mashups. Dailies stay out, since their sources are the
+/// answers to a puzzle that may still be played.
+fn history_select(query: &HistoryQuery) -> Result<sb::SelectBuilder> {
+    let mut select = sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .select()
+        .is("dailyDate", sb::IsValue::Null);
+    if let Some(language) = query.language {
+        let column = "track1->origin->>language";
+        select = if language == Language::default() {
+            // Mashups from before languages existed have none recorded
+            select.or(vec![
+                sb::Filter::eq(column, language.code()),
+                sb::Filter::is(column, sb::IsValue::Null),
+            ])
+        } else {
+            select.eq(column, language.code())
+        };
+    }
+    Ok(select
+        .order("createdAt", sb::OrderDirection::DESC)
+        .limit(HISTORY_LIMIT))
+}
+
+pub async fn history(query: &HistoryQuery) -> Result<Vec<MashupAssets>> {
+    let assets = history_select(query)?.request::<MashupAssets>().await?;
+    info!("Select {} history assets", assets.len());
+    Ok(assets)
+}
+
+pub async fn history_summaries(query: &HistoryQuery) -> Result<Vec<MashupSummary>> {
+    let summaries = history_select(query)?
+        .columns(summary_columns())
+        .request::<MashupSummary>()
+        .await?;
+    info!("Select {} history summaries", summaries.len());
+    Ok(summaries)
+}
+
 fn chunk_string(s: &str, chunk_size: usize) -> Vec<String> {
     let mut chunks = Vec::new();
     let mut start = 0;
 
     while start < s.len() {
-        let end = cmp::min(start + chunk_size, s.len());
+        let mut end = cmp::min(start + chunk_size, s.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == start {
+            // The chunk can't hold a single character, so take one whole
+            end += s[start..].chars().next().map_or(0, char::len_utf8);
+        }
         let chunk = &s[start..end];
         chunks.push(chunk.to_string());
         start = end;
@@ -74,18 +293,68 @@ async fn refresh_assets_cache(
     Ok(())
 }
 
-pub async fn refresh_assets(client: &Data<Arc<Client>>) -> Result<()> {
-    insert_new_asset_row().await?;
-    let assets = select_assets_from_database().await?;
-
+pub async fn refresh_assets(
+    client: &Data<Arc<Client>>,
+    provider: &dyn MusicProvider,
+    dictionary: &dyn Dictionary,
+    options: &RefreshOptions,
+) -> Result<()> {
+    let config = config::get()?;
+    let language = options.language.unwrap_or(config.language);
+    let words = words::from_spec(
+        options.words.as_deref().unwrap_or(&config.word_source),
+        language,
+        provider,
+    )?;
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let sources = Sources {
+        provider,
+        dictionary,
+        words: words.as_ref(),
+        language,
+    };
     let mut conn = client.get_multiplexed_tokio_connection().await?;
+    let mut mashers = FieldMashers::from_specs(
+        options.mashers.as_deref().unwrap_or(&config.name_mashers),
+        options
+            .artist_mashers
+            .as_deref()
+            .unwrap_or(&config.artist_mashers),
+        config,
+    )?;
+    mashers.markov = match markov::update_model(&mut conn).await {
+        Ok(model) => Some(Markov {
+            model,
+            temperature: config.markov_temperature,
+        }),
+        Err(err) => {
+            warn!("Failed to update Markov model: {}", err);
+            None
+        }
+    };
+    let row = generate_mashup(&sources, &mashers, seed, options.link, None).await?;
+    insert_asset_row(row).await?;
+    let assets = select_assets_from_database().await?;
     refresh_assets_cache(&assets, &mut conn).await?;
 
-    let keep_ids: Vec<String> = assets.iter().map(|item| item.id.to_string()).collect();
+    // Prune to what `/history` can show, which includes the cached assets
+    let keep_ids: Vec<i64> = sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .select()
+        .columns(sb::Columns::new().column("id"))
+        .is("dailyDate", sb::IsValue::Null)
+        .order("createdAt", sb::OrderDirection::DESC)
+        .limit(HISTORY_LIMIT)
+        .request::<MashupId>()
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
     let deleted = sb::SupabaseClient::new()?
         .from("mashup_assets")
         .delete()
-        .notin("id", keep_ids)
+        .not(sb::Filter::in_values("id", keep_ids))
+        .is("dailyDate", sb::IsValue::Null)
         .request::<MashupAssets>()
         .await?;
     info!("Deleted: {} assets", deleted.len());
@@ -132,3 +401,24 @@ pub async fn retrieve_assets(client: &Data<Arc<Client>>) -> Result<Vec<MashupAss
     info!("Retrieved assets from cache");
     Ok(assets)
 }
+
+/// The current mashups without previews, from the same cache as
+/// `retrieve_assets`.
+pub async fn retrieve_summaries(client: &Data<Arc<Client>>) -> Result<Vec<MashupSummary>> {
+    let assets = retrieve_assets(client).await?;
+    Ok(assets.iter().map(MashupSummary::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_on_char_boundaries() {
+        let s = "añ日b";
+        assert_eq!(chunk_string(s, 2), ["a", "ñ", "日", "b"]);
+        assert_eq!(chunk_string(s, 4), ["añ", "日b"]);
+        assert_eq!(chunk_string(s, 1), ["a", "ñ", "日", "b"]);
+        assert_eq!(chunk_string(s, 7).concat(), s);
+    }
+}
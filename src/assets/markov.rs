@@ -0,0 +1,251 @@
+use super::mash::{Alternating, Budget, MashContext, NameMasher};
+use super::normalize::{normalize_title, simplify};
+use super::track::SeededRng;
+use crate::{
+    apis::supabase::{self as sb, Filterable},
+    Result,
+};
+use log::{info, warn};
+use rand::{distributions::WeightedIndex, prelude::Distribution};
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use unicode_segmentation::UnicodeSegmentation;
+
+const MODEL_KEY: &str = "markov_model";
+
+/// Characters of context per transition.
+const ORDER: usize = 3;
+const START: char = '\u{2}';
+const END: &str = "";
+
+/// How much more the two songs being mashed count than the rest of the
+/// archive, so the result leans towards them.
+const SOURCE_WEIGHT: u32 = 3;
+const ATTEMPTS: usize = 20;
+const MIN_GRAPHEMES: usize = 3;
+
+/// A character-level Markov chain over every title seen so far. Maps are
+/// ordered so the same seed always walks the chain the same way.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct MarkovModel {
+    transitions: BTreeMap<String, BTreeMap<String, u32>>,
+
+    /// Simplified titles, so real ones aren't reproduced verbatim.
+    titles: BTreeSet<String>,
+
+    /// Highest `mashup_assets` id trained on.
+    #[serde(rename = "lastId")]
+    last_id: i64,
+}
+
+impl MarkovModel {
+    pub fn train(&mut self, title: &str) {
+        let title = title.trim();
+        if title.is_empty() {
+            return;
+        }
+        self.titles.insert(simplify(title));
+        let mut state: VecDeque<char> = std::iter::repeat_n(START, ORDER).collect();
+        for next in title.chars().map(String::from).chain([END.to_string()]) {
+            *self
+                .transitions
+                .entry(state.iter().collect())
+                .or_default()
+                .entry(next.clone())
+                .or_default() += 1;
+            if let Some(c) = next.chars().next() {
+                state.pop_front();
+                state.push_back(c);
+            }
+        }
+    }
+
+    fn train_row(&mut self, row: &MarkovRow) {
+        self.train(&song_title(&row.track1_base_title, &row.track1_full_title));
+        self.train(&song_title(&row.track2_base_title, &row.track2_full_title));
+        self.train(&row.title);
+        self.last_id = self.last_id.max(row.id);
+    }
+
+    /// Transitions out of `state`, with those of `sources` weighted up.
+    fn candidates<'a>(&'a self, sources: &'a MarkovModel, state: &str) -> BTreeMap<&'a str, u32> {
+        let mut candidates = BTreeMap::new();
+        for (model, weight) in [(self, 1), (sources, SOURCE_WEIGHT)] {
+            for (next, count) in model.transitions.get(state).into_iter().flatten() {
+                *candidates.entry(next.as_str()).or_default() += count * weight;
+            }
+        }
+        candidates
+    }
+
+    /// Samples the next character, or `END`. Temperatures below 1 favour
+    /// the likeliest transitions, above 1 flatten them out.
+    fn next<'a>(
+        &'a self,
+        sources: &'a MarkovModel,
+        state: &str,
+        temperature: f64,
+        rng: &mut SeededRng,
+    ) -> Option<&'a str> {
+        let candidates = self.candidates(sources, state);
+        let exponent = 1.0 / temperature.max(0.05);
+        let weights = WeightedIndex::new(
+            candidates
+                .values()
+                .map(|count| (*count as f64).powf(exponent)),
+        )
+        .ok()?;
+        candidates.keys().nth(weights.sample(rng)).copied()
+    }
+
+    /// Walks the chain, steered towards the titles in `sources`, until it
+    /// ends, retrying titles that overrun `budget`, are too short, or
+    /// already exist.
+    pub fn generate(
+        &self,
+        sources: &MarkovModel,
+        temperature: f64,
+        budget: Budget,
+        rng: &mut SeededRng,
+    ) -> Option<String> {
+        'attempt: for _ in 0..ATTEMPTS {
+            let mut state: VecDeque<char> = std::iter::repeat_n(START, ORDER).collect();
+            let mut title = String::new();
+            loop {
+                let state_key = state.iter().collect::<String>();
+                let next = self.next(sources, &state_key, temperature, rng)?;
+                let Some(c) = next.chars().next() else {
+                    break;
+                };
+                title.push(c);
+                if !budget.fits(&title) {
+                    continue 'attempt;
+                }
+                state.pop_front();
+                state.push_back(c);
+            }
+            let title = title.trim();
+            let simplified = simplify(title);
+            if title.graphemes(true).count() >= MIN_GRAPHEMES
+                && !self.titles.contains(&simplified)
+                && !sources.titles.contains(&simplified)
+            {
+                return Some(title.to_string());
+            }
+        }
+        None
+    }
+}
+
+/// The titles of a stored mashup, without the rest of the row.
+#[derive(Debug, Deserialize)]
+struct MarkovRow {
+    id: i64,
+
+    #[serde(rename = "track1BaseTitle")]
+    track1_base_title: Option<String>,
+
+    #[serde(rename = "track1FullTitle")]
+    track1_full_title: String,
+
+    #[serde(rename = "track2BaseTitle")]
+    track2_base_title: Option<String>,
+
+    #[serde(rename = "track2FullTitle")]
+    track2_full_title: String,
+    title: String,
+}
+
+/// Same as `TrackAsset::song_title`, for rows stored before `baseTitle`.
+fn song_title(base_title: &Option<String>, full_title: &str) -> String {
+    match base_title {
+        Some(base) if !base.is_empty() => base.clone(),
+        _ => normalize_title(full_title).base,
+    }
+}
+
+fn markov_columns() -> sb::Columns {
+    sb::Columns::new()
+        .column("id")
+        .alias("track1BaseTitle", "track1->>baseTitle")
+        .alias("track1FullTitle", "track1->>fullTitle")
+        .alias("track2BaseTitle", "track2->>baseTitle")
+        .alias("track2FullTitle", "track2->>fullTitle")
+        .alias("title", "mashedTrack->>title")
+}
+
+/// A trained model plus how adventurous its titles should be.
+pub struct Markov {
+    pub model: MarkovModel,
+    pub temperature: f64,
+}
+
+/// Generates a new title from the archive's model, steered by both source
+/// titles. Without a model, e.g. for daily mashups, it alternates words.
+pub struct MarkovTitles;
+
+impl NameMasher for MarkovTitles {
+    fn name(&self) -> &'static str {
+        "markov"
+    }
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String {
+        self.mash_with_strategy(name1, name2, context, rng).0
+    }
+
+    fn mash_with_strategy(
+        &self,
+        name1: &str,
+        name2: &str,
+        context: &MashContext,
+        rng: &mut SeededRng,
+    ) -> (String, &'static str) {
+        let generated = context.markov.and_then(|markov| {
+            let mut sources = MarkovModel::default();
+            sources.train(name1);
+            sources.train(name2);
+            markov
+                .model
+                .generate(&sources, markov.temperature, context.budget, rng)
+        });
+        match generated {
+            Some(title) => (title, self.name()),
+            None => Alternating.mash_with_strategy(name1, name2, context, rng),
+        }
+    }
+}
+
+async fn load_model(conn: &mut MultiplexedConnection) -> Result<MarkovModel> {
+    match conn.get::<_, Option<String>>(MODEL_KEY).await? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => {
+            warn!("No Markov model cached, building from scratch");
+            Ok(MarkovModel::default())
+        }
+    }
+}
+
+/// Trains the persisted model on mashups added since it was last updated
+/// and saves it back, so the model outlives the rows refreshes delete.
+pub async fn update_model(conn: &mut MultiplexedConnection) -> Result<MarkovModel> {
+    let mut model = load_model(conn).await?;
+    let rows = sb::SupabaseClient::new()?
+        .from("mashup_assets")
+        .select()
+        .columns(markov_columns())
+        .gt("id", model.last_id)
+        .order("id", sb::OrderDirection::ASC)
+        .request::<MarkovRow>()
+        .await?;
+    for row in &rows {
+        model.train_row(row);
+    }
+    let _: () = conn.set(MODEL_KEY, serde_json::to_string(&model)?).await?;
+    info!(
+        "Trained Markov model on {} new mashups ({} titles)",
+        rows.len(),
+        model.titles.len()
+    );
+    Ok(model)
+}
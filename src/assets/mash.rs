@@ -0,0 +1,492 @@
+use super::markov::{Markov, MarkovTitles};
+use super::models::{MashStrategies, MashedTrackAsset, MasherSpecs, TrackAsset};
+use super::normalize::normalize_title;
+use super::portmanteau::SyllablePortmanteau;
+use super::track::SeededRng;
+use crate::{config::Config, language::Language, Error, Result};
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TEMPLATES: [&str; 6] = [
+    "{a} & {b}",
+    "{a} vs. {b}",
+    "The {a} of {b}",
+    "{a} Meets {b}",
+    "{a}, {b}",
+    "{a} (feat. {b})",
+];
+
+/// The maximum length of a mashed field, in grapheme clusters or, for
+/// scripts with double-width characters, in terminal display columns.
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    pub limit: usize,
+    pub by_width: bool,
+}
+
+impl Budget {
+    pub fn measure(&self, s: &str) -> usize {
+        if self.by_width {
+            s.width()
+        } else {
+            s.graphemes(true).count()
+        }
+    }
+
+    pub fn fits(&self, s: &str) -> bool {
+        self.measure(s) <= self.limit
+    }
+
+    /// Cuts `s` down to the budget, at a word boundary where possible.
+    pub fn fit(&self, s: &str) -> String {
+        if self.fits(s) {
+            return s.to_string();
+        }
+        let mut result = String::new();
+        for word in s.split_word_bounds() {
+            if !self.fits(&format!("{result}{word}")) {
+                break;
+            }
+            result.push_str(word);
+        }
+        if result.trim().is_empty() {
+            for grapheme in s.graphemes(true) {
+                if !self.fits(&format!("{result}{grapheme}")) {
+                    break;
+                }
+                result.push_str(grapheme);
+            }
+        }
+        result
+            .trim_end_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+            .to_string()
+    }
+}
+
+/// What a masher needs to know about the field it is mashing.
+pub struct MashContext<'a> {
+    pub language: Language,
+    pub budget: Budget,
+
+    /// The archive's title model, for the "markov" strategy.
+    pub markov: Option<&'a Markov>,
+}
+
+/// Combines two names, e.g. two track titles, into a mashed name.
+pub trait NameMasher: Send + Sync {
+    /// Identifies the strategy on `MashedTrackAsset`.
+    fn name(&self) -> &'static str;
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String;
+
+    /// Mashes and names the strategy that actually produced the result, for
+    /// mashers that fall back to another.
+    fn mash_with_strategy(
+        &self,
+        name1: &str,
+        name2: &str,
+        context: &MashContext,
+        rng: &mut SeededRng,
+    ) -> (String, &'static str) {
+        (self.mash(name1, name2, context, rng), self.name())
+    }
+}
+
+/// Splits a title into the words to alternate. Unspaced scripts are split
+/// into chunks of two grapheme clusters instead.
+fn title_words(title: &str, language: Language) -> Vec<&str> {
+    if language.is_spaced() {
+        return title.split_whitespace().collect();
+    }
+    title
+        .split_whitespace()
+        .flat_map(|segment| {
+            if segment.is_ascii() {
+                return vec![segment];
+            }
+            let bounds: Vec<usize> = segment
+                .grapheme_indices(true)
+                .map(|(i, _)| i)
+                .step_by(2)
+                .chain([segment.len()])
+                .collect();
+            bounds.windows(2).map(|w| &segment[w[0]..w[1]]).collect()
+        })
+        .collect()
+}
+
+/// Joins words back together, only adding spaces between latin words in
+/// unspaced languages.
+fn join_words(words: &[&str], language: Language) -> String {
+    if language.is_spaced() {
+        return words.join(" ");
+    }
+    let mut result = String::new();
+    for word in words {
+        let needs_space = result
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+            || word
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric());
+        if !result.is_empty() && needs_space {
+            result.push(' ');
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+/// The longest word of a name, usually the most distinctive one.
+fn key_word(name: &str, language: Language) -> &str {
+    title_words(name, language)
+        .into_iter()
+        .rev()
+        .max_by_key(|w| {
+            w.graphemes(true)
+                .filter(|g| g.chars().any(char::is_alphanumeric))
+                .count()
+        })
+        .unwrap_or(name)
+}
+
+fn ordered<'a>(name1: &'a str, name2: &'a str, rng: &mut SeededRng) -> (&'a str, &'a str) {
+    if rng.gen::<bool>() {
+        (name1, name2)
+    } else {
+        (name2, name1)
+    }
+}
+
+/// Alternates the words of both names.
+pub struct Alternating;
+
+impl NameMasher for Alternating {
+    fn name(&self) -> &'static str {
+        "alternating"
+    }
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String {
+        let (first, second) = ordered(name1, name2, rng);
+        let words1 = title_words(first, context.language);
+        let words2 = title_words(second, context.language);
+
+        let mut result: Vec<&str> = Vec::new();
+        let len = words1.len().max(words2.len());
+        let alternating = (0..len)
+            .flat_map(|i| [words1.get(i), words2.get(i)])
+            .flatten();
+
+        for word in alternating {
+            result.push(word);
+            if result.len() > 1 && !context.budget.fits(&join_words(&result, context.language)) {
+                result.pop();
+                break;
+            }
+        }
+        join_words(&result, context.language)
+    }
+}
+
+/// The first half of one name's words followed by the second half of the other's.
+pub struct Splice;
+
+impl NameMasher for Splice {
+    fn name(&self) -> &'static str {
+        "splice"
+    }
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String {
+        let (first, second) = ordered(name1, name2, rng);
+        let words1 = title_words(first, context.language);
+        let words2 = title_words(second, context.language);
+        let head = &words1[..words1.len().div_ceil(2)];
+        let tail = &words2[words2.len() / 2..];
+        join_words(&[head, tail].concat(), context.language)
+    }
+}
+
+/// Joins the front of one name's key word to the back of the other's.
+pub struct KeywordPortmanteau;
+
+impl NameMasher for KeywordPortmanteau {
+    fn name(&self) -> &'static str {
+        "portmanteau"
+    }
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String {
+        let (first, second) = ordered(name1, name2, rng);
+        let key1: Vec<&str> = key_word(first, context.language).graphemes(true).collect();
+        let key2: Vec<&str> = key_word(second, context.language).graphemes(true).collect();
+        key1[..key1.len().div_ceil(2)]
+            .iter()
+            .chain(&key2[key2.len() / 2..])
+            .copied()
+            .collect()
+    }
+}
+
+/// Takes each letter of the key words alternately from one and the other.
+pub struct InterleavedLetters;
+
+impl NameMasher for InterleavedLetters {
+    fn name(&self) -> &'static str {
+        "interleaved"
+    }
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String {
+        let (first, second) = ordered(name1, name2, rng);
+        let key1: Vec<&str> = key_word(first, context.language).graphemes(true).collect();
+        let key2: Vec<&str> = key_word(second, context.language).graphemes(true).collect();
+        (0..key1.len().max(key2.len()))
+            .filter_map(|i| match i % 2 {
+                0 => key1.get(i).or(key2.get(i)),
+                _ => key2.get(i).or(key1.get(i)),
+            })
+            .copied()
+            .collect()
+    }
+}
+
+/// Fills a phrase template with the key word of each name.
+pub struct Template;
+
+impl NameMasher for Template {
+    fn name(&self) -> &'static str {
+        "template"
+    }
+
+    fn mash(&self, name1: &str, name2: &str, context: &MashContext, rng: &mut SeededRng) -> String {
+        let (first, second) = ordered(name1, name2, rng);
+        let template = TEMPLATES.choose(rng).unwrap_or(&TEMPLATES[0]);
+        template
+            .replace("{a}", key_word(first, context.language))
+            .replace("{b}", key_word(second, context.language))
+    }
+}
+
+fn masher_from_name(name: &str) -> Option<Box<dyn NameMasher>> {
+    match name {
+        "alternating" => Some(Box::new(Alternating)),
+        "splice" => Some(Box::new(Splice)),
+        "portmanteau" => Some(Box::new(KeywordPortmanteau)),
+        "interleaved" => Some(Box::new(InterleavedLetters)),
+        "template" => Some(Box::new(Template)),
+        "markov" => Some(Box::new(MarkovTitles)),
+        "syllables" => Some(Box::new(SyllablePortmanteau {
+            deterministic: false,
+        })),
+        "syllables-fixed" => Some(Box::new(SyllablePortmanteau {
+            deterministic: true,
+        })),
+        _ => None,
+    }
+}
+
+/// Strategies picked from at random by weight for each mashed field.
+pub struct MasherSet {
+    mashers: Vec<Box<dyn NameMasher>>,
+    weights: WeightedIndex<u32>,
+}
+
+impl MasherSet {
+    /// Parses a spec such as `alternating:4,splice:1`. A strategy without a
+    /// weight gets a weight of 1, so `splice` alone always splices.
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        let mut mashers = Vec::new();
+        let mut weights = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, weight) = match entry.split_once(':') {
+                Some((name, weight)) => (
+                    name,
+                    weight
+                        .parse()
+                        .map_err(|_| Error::Custom(format!("Invalid masher weight '{entry}'")))?,
+                ),
+                None => (entry, 1),
+            };
+            let masher = masher_from_name(name)
+                .ok_or(Error::Custom(format!("Unknown name masher '{name}'")))?;
+            mashers.push(masher);
+            weights.push(weight);
+        }
+        let weights = WeightedIndex::new(&weights)
+            .map_err(|e| Error::Custom(format!("Invalid masher spec '{spec}': {e}")))?;
+        Ok(Self { mashers, weights })
+    }
+
+    fn choose(&self, rng: &mut SeededRng) -> &dyn NameMasher {
+        self.mashers[self.weights.sample(rng)].as_ref()
+    }
+}
+
+/// The strategies and length budget for a mashed field.
+pub struct FieldMasher {
+    pub mashers: MasherSet,
+    pub budget: Budget,
+}
+
+impl FieldMasher {
+    fn mash(
+        &self,
+        name1: &str,
+        name2: &str,
+        language: Language,
+        markov: Option<&Markov>,
+        rng: &mut SeededRng,
+    ) -> (String, &'static str) {
+        let masher = self.mashers.choose(rng);
+        let context = MashContext {
+            language,
+            budget: self.budget,
+            markov,
+        };
+        let (mashed, strategy) = masher.mash_with_strategy(name1, name2, &context, rng);
+        (self.budget.fit(&mashed), strategy)
+    }
+}
+
+/// How each mashed field is produced. Artists get their own strategies, since
+/// names like "Artist1 Artist2" read better as a single portmanteau.
+pub struct FieldMashers {
+    pub title: FieldMasher,
+    pub artist: FieldMasher,
+    pub album_title: FieldMasher,
+
+    /// Loaded separately, see `markov::update_model`.
+    pub markov: Option<Markov>,
+
+    /// The specs these were built from.
+    pub specs: MasherSpecs,
+}
+
+impl FieldMashers {
+    pub fn from_specs(names: &str, artists: &str, config: &Config) -> Result<Self> {
+        let budget = |limit| Budget {
+            limit,
+            by_width: config.limit_by_width,
+        };
+        Ok(Self {
+            title: FieldMasher {
+                mashers: MasherSet::from_spec(names)?,
+                budget: budget(config.title_limit),
+            },
+            artist: FieldMasher {
+                mashers: MasherSet::from_spec(artists)?,
+                budget: budget(config.artist_limit),
+            },
+            album_title: FieldMasher {
+                mashers: MasherSet::from_spec(names)?,
+                budget: budget(config.album_title_limit),
+            },
+            markov: None,
+            specs: MasherSpecs {
+                names: names.to_string(),
+                artists: artists.to_string(),
+            },
+        })
+    }
+}
+
+pub fn mash_track_assets(
+    track1: &TrackAsset,
+    track2: &TrackAsset,
+    mashers: &FieldMashers,
+    language: Language,
+    rng: &mut SeededRng,
+) -> MashedTrackAsset {
+    let markov = mashers.markov.as_ref();
+    // Versions and credits would crowd out the names' actual words
+    let (title, title_strategy) = mashers.title.mash(
+        &track1.song_title(),
+        &track2.song_title(),
+        language,
+        markov,
+        rng,
+    );
+    let (artist, artist_strategy) = mashers.artist.mash(
+        &track1.main_artist(),
+        &track2.main_artist(),
+        language,
+        markov,
+        rng,
+    );
+    let (album_title, album_strategy) = mashers.album_title.mash(
+        &normalize_title(&track1.album_title).base,
+        &normalize_title(&track2.album_title).base,
+        language,
+        markov,
+        rng,
+    );
+    MashedTrackAsset {
+        title,
+        artist,
+        album_title,
+        strategies: MashStrategies {
+            title: title_strategy.into(),
+            artist: artist_strategy.into(),
+            album_title: album_strategy.into(),
+        },
+        description: String::new(),
+        cover: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPHEMES: Budget = Budget {
+        limit: 10,
+        by_width: false,
+    };
+
+    #[test]
+    fn fits_non_ascii_at_word_boundaries() {
+        assert_eq!(GRAPHEMES.fit("Ça ira, ça ira, ça ira"), "Ça ira, ça");
+
+        // Combining accents count once, and long words are cut by grapheme
+        let word = "e\u{301}".repeat(20);
+        let budget = Budget {
+            limit: 5,
+            by_width: false,
+        };
+        assert_eq!(budget.fit(&word), "e\u{301}".repeat(5));
+    }
+
+    #[test]
+    fn fits_cjk_by_width() {
+        let budget = Budget {
+            limit: 5,
+            by_width: true,
+        };
+        assert_eq!(budget.measure("東京事変"), 8);
+        assert_eq!(budget.fit("東京事変"), "東京");
+        assert_eq!(GRAPHEMES.fit("東京事変"), "東京事変");
+    }
+
+    #[test]
+    fn fits_empty_input_and_zero_limits() {
+        assert_eq!(GRAPHEMES.fit(""), "");
+        for by_width in [false, true] {
+            let budget = Budget { limit: 0, by_width };
+            assert!(budget.fits(""));
+            assert_eq!(budget.fit("Abba"), "");
+            assert_eq!(budget.fit("東京"), "");
+        }
+    }
+
+    #[test]
+    fn splits_unspaced_titles_by_grapheme() {
+        assert_eq!(title_words("東京事変", Language::Ja), ["東京", "事変"]);
+        assert_eq!(
+            title_words("か\u{3099}きくけ", Language::Ja),
+            ["か\u{3099}き", "くけ"]
+        );
+        assert_eq!(title_words("Love 東京", Language::Ja), ["Love", "東京"]);
+        assert!(title_words("", Language::Zh).is_empty());
+    }
+}
@@ -1,3 +1,14 @@
+pub mod artwork;
+pub mod cover;
+pub mod daily;
+pub mod link;
 pub mod manager;
+pub mod markov;
+pub mod mash;
 pub mod models;
+pub mod normalize;
+pub mod notes;
+pub mod palette;
+pub mod portmanteau;
 pub mod track;
+pub mod words;
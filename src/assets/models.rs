@@ -1,8 +1,30 @@
-use crate::apis::{deezer::Track, dictionary::Word};
+use super::cover::CoverLayout;
+use super::link::LinkMode;
+use super::normalize::{normalize_artist, normalize_title};
+use super::palette::Palette;
+use crate::{
+    apis::dictionary::Word,
+    language::Language,
+    providers::{MusicProvider, Track},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MashupAssetsInsert {
+    /// Seed the mashup was generated from, as a string so it survives
+    /// JavaScript's number precision.
+    pub seed: String,
+
+    #[serde(rename = "dailyDate", skip_serializing_if = "Option::is_none")]
+    pub daily_date: Option<String>,
+
+    #[serde(rename = "wordLink")]
+    pub word_link: Option<WordLink>,
+
+    /// With `seed`, everything needed to generate the mashup again, given
+    /// the same Markov model.
+    pub mashers: MasherSpecs,
+    pub link: Option<LinkMode>,
     pub track1: TrackAsset,
     pub track2: TrackAsset,
 
@@ -12,10 +34,26 @@ pub struct MashupAssetsInsert {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MashupAssets {
-    pub id: i8,
+    pub id: i64,
 
     #[serde(rename = "createdAt")]
     pub created_at: String,
+
+    #[serde(default)]
+    pub seed: Option<String>,
+
+    #[serde(rename = "dailyDate", default)]
+    pub daily_date: Option<String>,
+
+    #[serde(rename = "wordLink", default)]
+    pub word_link: Option<WordLink>,
+
+    /// Not recorded for mashups from before they were stored.
+    #[serde(default)]
+    pub mashers: Option<MasherSpecs>,
+
+    #[serde(default)]
+    pub link: Option<LinkMode>,
     pub track1: TrackAsset,
     pub track2: TrackAsset,
 
@@ -23,43 +61,222 @@ pub struct MashupAssets {
     pub mashed_track: MashedTrackAsset,
 }
 
+/// A mashup without audio or image payloads, for listings. Flat, since it
+/// is assembled from JSON paths into the track columns.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MashupSummary {
+    pub id: i64,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+
+    #[serde(default)]
+    pub seed: Option<String>,
+
+    #[serde(rename = "dailyDate", default)]
+    pub daily_date: Option<String>,
+
+    #[serde(rename = "wordLink", default)]
+    pub word_link: Option<WordLink>,
+    pub title: String,
+    pub artist: String,
+
+    #[serde(rename = "albumTitle")]
+    pub album_title: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(rename = "track1Title")]
+    pub track1_title: String,
+
+    #[serde(rename = "track1Artist")]
+    pub track1_artist: String,
+
+    #[serde(rename = "track1CoverUrl")]
+    pub track1_cover_url: String,
+
+    #[serde(rename = "track1Blurhash", default)]
+    pub track1_blurhash: Option<String>,
+
+    #[serde(rename = "track2Title")]
+    pub track2_title: String,
+
+    #[serde(rename = "track2Artist")]
+    pub track2_artist: String,
+
+    #[serde(rename = "track2CoverUrl")]
+    pub track2_cover_url: String,
+
+    #[serde(rename = "track2Blurhash", default)]
+    pub track2_blurhash: Option<String>,
+}
+
+impl From<&MashupAssets> for MashupSummary {
+    fn from(assets: &MashupAssets) -> Self {
+        let description = &assets.mashed_track.description;
+        Self {
+            id: assets.id,
+            created_at: assets.created_at.clone(),
+            seed: assets.seed.clone(),
+            daily_date: assets.daily_date.clone(),
+            word_link: assets.word_link.clone(),
+            title: assets.mashed_track.title.clone(),
+            artist: assets.mashed_track.artist.clone(),
+            album_title: assets.mashed_track.album_title.clone(),
+            description: (!description.is_empty()).then(|| description.clone()),
+            track1_title: assets.track1.title.clone(),
+            track1_artist: assets.track1.artist.clone(),
+            track1_cover_url: assets.track1.cover_url.clone(),
+            track1_blurhash: assets.track1.blurhash.clone(),
+            track2_title: assets.track2.title.clone(),
+            track2_artist: assets.track2.artist.clone(),
+            track2_cover_url: assets.track2.cover_url.clone(),
+            track2_blurhash: assets.track2.blurhash.clone(),
+        }
+    }
+}
+
+/// The `MasherSet` specs a mashup's fields were mashed with.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MasherSpecs {
+    pub names: String,
+    pub artists: String,
+}
+
+/// How the two seed words of a linked mashup relate to each other.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WordRelation {
+    Synonym,
+    Antonym,
+    Etymology,
+    Rhyme,
+    PartOfSpeech,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WordLink {
+    pub relation: WordRelation,
+    pub from: String,
+    pub to: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TrackAsset {
     pub id: u64,
+
+    #[serde(default = "default_provider")]
+    pub provider: String,
     pub title: String,
 
     #[serde(rename = "fullTitle")]
     pub full_title: String,
+
+    /// `full_title` without its version or featured artists, see
+    /// `normalize_title`. Empty on assets stored before normalization.
+    #[serde(rename = "baseTitle", default)]
+    pub base_title: String,
+
+    #[serde(default)]
+    pub version: Option<String>,
+
+    #[serde(rename = "featuredArtists", default)]
+    pub featured_artists: Vec<String>,
     pub artist: String,
     pub preview: String,
 
+    /// MIME type of the base64 `preview`.
+    #[serde(rename = "previewType", default = "default_preview_type")]
+    pub preview_type: String,
+
     #[serde(rename = "albumTitle")]
     pub album_title: String,
 
     #[serde(rename = "coverUrl")]
     pub cover_url: String,
+
+    /// Placeholder for the cover while the proxied image loads.
+    #[serde(default)]
+    pub blurhash: Option<String>,
+
+    #[serde(default)]
+    pub palette: Option<Palette>,
     pub origin: TrackOrigin,
 }
 
+fn default_provider() -> String {
+    "deezer".into()
+}
+
+fn default_preview_type() -> String {
+    "audio/mpeg".into()
+}
+
 impl TrackAsset {
-    pub fn from_track(track: Track, preview: String, origin: TrackOrigin) -> Self {
+    pub fn from_track(
+        provider: &dyn MusicProvider,
+        track: Track,
+        preview: String,
+        origin: TrackOrigin,
+    ) -> Self {
+        let normalized = normalize_title(&track.full_title);
+        let (_, artist_featured) = normalize_artist(&track.artist);
+        let mut featured_artists = normalized.featured;
+        for name in artist_featured {
+            if !featured_artists.contains(&name) {
+                featured_artists.push(name);
+            }
+        }
         Self {
             id: track.id,
+            provider: provider.name().to_string(),
             title: track.title,
             full_title: track.full_title,
-            artist: track.artist.name,
+            base_title: normalized.base,
+            version: normalized.version,
+            featured_artists,
+            artist: track.artist,
             preview,
-            album_title: track.album.title,
-            cover_url: track.album.cover_url,
+            preview_type: provider.preview_type().to_string(),
+            album_title: track.album_title,
+            cover_url: track.cover_url,
+            blurhash: None,
+            palette: None,
             origin,
         }
     }
+
+    /// The song's name without versions or credits, normalizing on the fly
+    /// for assets stored before `base_title` existed.
+    pub fn song_title(&self) -> String {
+        if self.base_title.is_empty() {
+            normalize_title(&self.full_title).base
+        } else {
+            self.base_title.clone()
+        }
+    }
+
+    pub fn main_artist(&self) -> String {
+        normalize_artist(&self.artist).0
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TrackOrigin {
     pub word: Word,
 
+    /// Base64 recording of the seed word's pronunciation, when available.
+    #[serde(default)]
+    pub pronunciation: Option<String>,
+
+    /// `WordSource::name`, e.g. `themed:animals` or `file:words.txt`.
+    #[serde(rename = "wordSource", default)]
+    pub word_source: String,
+
+    #[serde(default)]
+    pub language: Language,
+
     #[serde(rename = "totalTracks")]
     pub total_tracks: u64,
 
@@ -74,4 +291,39 @@ pub struct MashedTrackAsset {
 
     #[serde(rename = "albumTitle")]
     pub album_title: String,
+
+    #[serde(default)]
+    pub strategies: MashStrategies,
+
+    /// Liner notes on where the mashup came from, see `notes::liner_notes`.
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<MashedCover>,
+}
+
+/// Artwork composited from both tracks' album covers.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MashedCover {
+    pub layout: CoverLayout,
+
+    /// Whether the mashed title was rendered onto the image.
+    pub titled: bool,
+
+    /// Base64 JPEG, served by `/mashups/{id}/cover`.
+    pub image: String,
+
+    #[serde(default)]
+    pub palette: Option<Palette>,
+}
+
+/// Names of the `NameMasher` strategies that produced each mashed field.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct MashStrategies {
+    pub title: String,
+    pub artist: String,
+
+    #[serde(rename = "albumTitle")]
+    pub album_title: String,
 }
@@ -0,0 +1,354 @@
+/// Words that mark a bracketed or dashed suffix as a version of the song
+/// rather than part of its name, e.g. "(Live)" or "- Remastered 2011".
+const VERSION_WORDS: [&str; 25] = [
+    "acoustic",
+    "bonus",
+    "deluxe",
+    "demo",
+    "edit",
+    "edition",
+    "explicit",
+    "extended",
+    "instrumental",
+    "karaoke",
+    "live",
+    "mix",
+    "mono",
+    "orchestral",
+    "radio",
+    "re-recorded",
+    "remaster",
+    "remastered",
+    "remix",
+    "reprise",
+    "session",
+    "stereo",
+    "taylor's",
+    "unplugged",
+    "version",
+];
+
+/// Introduce a list of featured artists. "with" only counts in a suffix,
+/// since inline it is usually part of the name ("Dancing with Myself").
+const FEATURING: [&str; 6] = ["feat.", "feat", "ft.", "ft", "featuring", "with"];
+
+/// A title split into the name of the song, the version of it, and any
+/// artists credited alongside the main one.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NormalizedTitle {
+    pub base: String,
+    pub version: Option<String>,
+    pub featured: Vec<String>,
+}
+
+fn words(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(|c: char| c.is_whitespace() || c == '/' || c == ',')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+}
+
+fn is_version(s: &str) -> bool {
+    words(s).any(|word| {
+        let word = word.trim_matches(|c: char| c.is_ascii_punctuation() && c != '\'');
+        VERSION_WORDS.contains(&word) || word.starts_with("remaster")
+    })
+}
+
+/// The featured artists in a bracketed or dashed suffix starting with
+/// "feat.", "ft." and the like.
+fn featured_list(s: &str) -> Option<Vec<String>> {
+    let (first, rest) = s.trim().split_once(char::is_whitespace)?;
+    if !FEATURING.contains(&first.to_lowercase().as_str()) {
+        return None;
+    }
+    Some(split_artists(rest))
+}
+
+/// Splits "A, B & C" into its artists.
+pub fn split_artists(s: &str) -> Vec<String> {
+    let mut artists = vec![s.to_string()];
+    for separator in [", ", " & ", " and ", " x ", " X "] {
+        artists = artists
+            .iter()
+            .flat_map(|a| a.split(separator))
+            .map(|a| a.trim().to_string())
+            .collect();
+    }
+    artists.retain(|a| !a.is_empty());
+    artists
+}
+
+/// Splits off an inline "feat." credit: "Song feat. X" -> ("Song", [X]).
+fn split_inline_featuring(s: &str) -> (String, Vec<String>) {
+    let lower = s.to_lowercase();
+    for marker in FEATURING.iter().filter(|m| **m != "with") {
+        let marker = format!(" {marker} ");
+        if let Some(start) = lower.find(&marker) {
+            // Lowercasing may change lengths outside ASCII, so only trust
+            // the offset when it lands on the same boundary in `s`
+            if s.is_char_boundary(start) && s.is_char_boundary(start + marker.len()) {
+                return (
+                    s[..start].trim().to_string(),
+                    split_artists(&s[start + marker.len()..]),
+                );
+            }
+        }
+    }
+    (s.trim().to_string(), Vec::new())
+}
+
+/// Parses titles like "Song (feat. X) [Radio Edit]", "Song - Remastered 2011"
+/// or "Song feat. X & Y". Bracketed parts that aren't credits or versions,
+/// as in "(I Can't Get No) Satisfaction", stay in the base title.
+pub fn normalize_title(title: &str) -> NormalizedTitle {
+    let mut base = String::new();
+    let mut versions: Vec<String> = Vec::new();
+    let mut featured: Vec<String> = Vec::new();
+
+    // Pull out top-level bracketed groups
+    let mut depth = 0usize;
+    let mut group = String::new();
+    for c in title.chars() {
+        match c {
+            '(' | '[' | '{' => {
+                if depth > 0 {
+                    group.push(c);
+                }
+                depth += 1;
+            }
+            ')' | ']' | '}' if depth > 0 => {
+                depth -= 1;
+                if depth > 0 {
+                    group.push(c);
+                    continue;
+                }
+                let inner = group.trim().to_string();
+                group.clear();
+                if let Some(artists) = featured_list(&inner) {
+                    featured.extend(artists);
+                } else if is_version(&inner) {
+                    versions.push(inner);
+                } else {
+                    base.push('(');
+                    base.push_str(&inner);
+                    base.push(')');
+                }
+            }
+            _ if depth > 0 => group.push(c),
+            _ => base.push(c),
+        }
+    }
+    // Unclosed bracket: keep what was in it
+    if depth > 0 {
+        base.push('(');
+        base.push_str(&group);
+    }
+
+    // Dashed suffixes, possibly several: "Song - Live - 2004 Remaster"
+    for separator in [" - ", " – ", " — "] {
+        while let Some((head, tail)) = base.rsplit_once(separator) {
+            let tail = tail.trim();
+            if let Some(artists) = featured_list(tail) {
+                featured.extend(artists);
+            } else if is_version(tail) {
+                versions.insert(0, tail.to_string());
+            } else {
+                break;
+            }
+            base = head.to_string();
+        }
+    }
+
+    let (base, inline) = split_inline_featuring(&base);
+    featured.extend(inline);
+
+    let base = base.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Nothing but a version or credit, e.g. "(Live)": that is the name
+    if base.is_empty() {
+        return NormalizedTitle {
+            base: title.trim().to_string(),
+            ..Default::default()
+        };
+    }
+    NormalizedTitle {
+        base,
+        version: (!versions.is_empty()).then(|| versions.join(", ")),
+        featured,
+    }
+}
+
+/// The main artist of an artist credit, e.g. "Calvin Harris feat. Rihanna"
+/// -> ("Calvin Harris", ["Rihanna"]).
+pub fn normalize_artist(artist: &str) -> (String, Vec<String>) {
+    let (main, featured) = split_inline_featuring(artist);
+    if main.is_empty() {
+        (artist.trim().to_string(), featured)
+    } else {
+        (main, featured)
+    }
+}
+
+/// Reduces a title to lowercase letters and digits for lenient comparison.
+pub fn simplify(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether a player's guess names the same song as `title`, ignoring case,
+/// punctuation, versions and featured artists on either side.
+pub fn matches_title(guess: &str, title: &str) -> bool {
+    let guess = simplify(&normalize_title(guess).base);
+    !guess.is_empty()
+        && (guess == simplify(&normalize_title(title).base) || guess == simplify(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title(base: &str, version: Option<&str>, featured: &[&str]) -> NormalizedTitle {
+        NormalizedTitle {
+            base: base.into(),
+            version: version.map(String::from),
+            featured: featured.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn normalizes_real_world_titles() {
+        let cases = [
+            // Featured artists
+            (
+                "Señorita (feat. Camila Cabello)",
+                title("Señorita", None, &["Camila Cabello"]),
+            ),
+            (
+                "Stay ft. Justin Bieber",
+                title("Stay", None, &["Justin Bieber"]),
+            ),
+            (
+                "Empire State of Mind - feat. Alicia Keys",
+                title("Empire State of Mind", None, &["Alicia Keys"]),
+            ),
+            (
+                "Lean On (feat. MØ & DJ Snake)",
+                title("Lean On", None, &["MØ", "DJ Snake"]),
+            ),
+            (
+                "Mi Gente (with Beyoncé)",
+                title("Mi Gente", None, &["Beyoncé"]),
+            ),
+            (
+                "Song feat. A, B & C [Radio Edit]",
+                title("Song", Some("Radio Edit"), &["A", "B", "C"]),
+            ),
+            (
+                "Dancing with Myself",
+                title("Dancing with Myself", None, &[]),
+            ),
+            // Versions
+            (
+                "Let It Be - Remastered 2009",
+                title("Let It Be", Some("Remastered 2009"), &[]),
+            ),
+            (
+                "Déjà Vu - Remastered 2011",
+                title("Déjà Vu", Some("Remastered 2011"), &[]),
+            ),
+            (
+                "Here Comes The Sun - 2019 Mix",
+                title("Here Comes The Sun", Some("2019 Mix"), &[]),
+            ),
+            (
+                "Song - Live - 2004 Remaster",
+                title("Song", Some("Live, 2004 Remaster"), &[]),
+            ),
+            (
+                "Bohemian Rhapsody (Live at Wembley 1986)",
+                title("Bohemian Rhapsody", Some("Live at Wembley 1986"), &[]),
+            ),
+            (
+                "Lose Yourself [Explicit]",
+                title("Lose Yourself", Some("Explicit"), &[]),
+            ),
+            (
+                "Blinding Lights (Radio Edit)",
+                title("Blinding Lights", Some("Radio Edit"), &[]),
+            ),
+            (
+                "Love Story (Taylor's Version)",
+                title("Love Story", Some("Taylor's Version"), &[]),
+            ),
+            (
+                "Café del Mar (Energy 52 Remix)",
+                title("Café del Mar", Some("Energy 52 Remix"), &[]),
+            ),
+            (
+                "Hold On — Extended Mix",
+                title("Hold On", Some("Extended Mix"), &[]),
+            ),
+            (
+                "Ünïcödé – Acoustic",
+                title("Ünïcödé", Some("Acoustic"), &[]),
+            ),
+            ("夜に駆ける (Live)", title("夜に駆ける", Some("Live"), &[])),
+            // Brackets and dashes that are part of the name
+            (
+                "(I Can't Get No) Satisfaction",
+                title("(I Can't Get No) Satisfaction", None, &[]),
+            ),
+            (
+                "Runaway (Cover Me in Sunshine)",
+                title("Runaway (Cover Me in Sunshine)", None, &[]),
+            ),
+            (
+                "Stay - Clean Getaway",
+                title("Stay - Clean Getaway", None, &[]),
+            ),
+            ("Für Elise", title("Für Elise", None, &[])),
+            ("Unclosed (bracket", title("Unclosed (bracket", None, &[])),
+            ("(Live)", title("(Live)", None, &[])),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(normalize_title(raw), expected, "{raw}");
+        }
+    }
+
+    #[test]
+    fn normalizes_artists() {
+        assert_eq!(
+            normalize_artist("Calvin Harris feat. Rihanna"),
+            ("Calvin Harris".to_string(), vec!["Rihanna".to_string()])
+        );
+        assert_eq!(
+            normalize_artist("Simon & Garfunkel"),
+            ("Simon & Garfunkel".to_string(), Vec::new())
+        );
+        assert_eq!(split_artists("A, B & C and D"), ["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn matches_guesses() {
+        let cases = [
+            ("let it be", "Let It Be - Remastered 2009", true),
+            ("Let It Be (Live)", "Let It Be - Remastered 2009", true),
+            ("DÉJÀ VU", "Déjà Vu - Remastered 2011", true),
+            ("senorita", "Señorita (feat. Camila Cabello)", false),
+            ("señorita", "Señorita (feat. Camila Cabello)", true),
+            (
+                "i cant get no satisfaction",
+                "(I Can't Get No) Satisfaction",
+                true,
+            ),
+            ("Stay", "Stay - Clean Getaway", false),
+            ("Runaway", "Runaway (Cover Me in Sunshine)", false),
+            ("", "Let It Be", false),
+            ("!!!", "Let It Be", false),
+        ];
+        for (guess, actual, expected) in cases {
+            assert_eq!(matches_title(guess, actual), expected, "{guess} / {actual}");
+        }
+    }
+}
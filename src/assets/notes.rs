@@ -0,0 +1,119 @@
+use super::models::{MashedTrackAsset, TrackAsset, WordLink, WordRelation};
+use super::track::SeededRng;
+use rand::seq::SliceRandom;
+
+/// How each source track's seed word is introduced. Placeholders: `{word}`,
+/// `{pos}`, `{definition}`, `{index}`, `{total}`. The tracks themselves are
+/// never named, since they are the answers to daily puzzles.
+const TRACK_TEMPLATES: [&str; 6] = [
+    "Pulled from result #{index} of {total} for \"{word}\" ({pos}): {definition}.",
+    "\"{word}\" ({pos}): {definition}. One of {total} songs took that literally.",
+    "Somewhere in {total} songs about \"{word}\" sat track #{index}, waiting.",
+    "Look up \"{word}\" and you'll find {definition}. Look a little harder and you'll find a song.",
+    "\"{word}\", as in {definition}, was one half of the recipe.",
+    "One ingredient: \"{word}\" ({pos}), meaning {definition}, dug out of {total} hits.",
+];
+
+/// Used when the dictionary had nothing on the seed word.
+const UNDEFINED_TEMPLATES: [&str; 3] = [
+    "Pulled from result #{index} of {total} for \"{word}\", whatever that means.",
+    "Nobody could define \"{word}\", but somebody still wrote a song about it. Probably.",
+    "Somewhere in {total} songs about \"{word}\" sat track #{index}, waiting.",
+];
+
+/// How the result is announced. Placeholders: `{title}`, `{artist}`, `{album}`.
+const MASHUP_TEMPLATES: [&str; 5] = [
+    "Together they become {title} by {artist}.",
+    "The result: {title}, the lead single from {artist}'s {album}.",
+    "Mash them up and you get {title}, as performed by {artist}.",
+    "{artist} proudly presents {title}, off the critically unacclaimed {album}.",
+    "File under {title} by {artist}, {album}.",
+];
+
+/// Formats a count with thousands separators, e.g. 12345 -> "12,345".
+fn with_separators(n: u64) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(c);
+    }
+    formatted
+}
+
+/// The first definition's part of speech, and the definition shortened to
+/// its first clause for use mid-sentence.
+fn short_definition(track: &TrackAsset) -> Option<(String, String)> {
+    let meaning = track.origin.word.meanings.as_ref()?.first()?;
+    let definition = meaning.definitions.first()?.definition.trim();
+    let clause = definition
+        .split(['.', ';'])
+        .next()
+        .unwrap_or(definition)
+        .trim();
+    let mut chars = clause.chars();
+    let first = chars.next()?;
+    Some((
+        meaning.part_of_speech.clone(),
+        first.to_lowercase().chain(chars).collect(),
+    ))
+}
+
+fn describe_track(track: &TrackAsset, rng: &mut SeededRng) -> String {
+    let definition = short_definition(track);
+    let templates: &[&str] = if definition.is_some() {
+        &TRACK_TEMPLATES
+    } else {
+        &UNDEFINED_TEMPLATES
+    };
+    let (pos, definition) = definition.unwrap_or_default();
+    templates
+        .choose(rng)
+        .unwrap_or(&templates[0])
+        .replace("{word}", &track.origin.word.word)
+        .replace("{pos}", &pos)
+        .replace("{definition}", &definition)
+        .replace("{index}", &with_separators(track.origin.track_index + 1))
+        .replace("{total}", &with_separators(track.origin.total_tracks))
+}
+
+fn describe_link(link: &WordLink) -> String {
+    let relation = match link.relation {
+        WordRelation::Synonym => "means much the same as",
+        WordRelation::Antonym => "is the opposite of",
+        WordRelation::Etymology => "shares its roots with",
+        WordRelation::Rhyme => "rhymes with",
+        WordRelation::PartOfSpeech => "is the same part of speech as",
+    };
+    format!(
+        "No coincidence: \"{}\" {} \"{}\".",
+        link.to, relation, link.from
+    )
+}
+
+/// Writes a few sentences of liner notes about where a mashup came from,
+/// without giving away the source tracks. All choices are drawn from `rng`,
+/// so a seed always yields the same notes.
+pub fn liner_notes(
+    track1: &TrackAsset,
+    track2: &TrackAsset,
+    mashed: &MashedTrackAsset,
+    word_link: Option<&WordLink>,
+    rng: &mut SeededRng,
+) -> String {
+    let mut sentences = vec![describe_track(track1, rng), describe_track(track2, rng)];
+    if let Some(link) = word_link {
+        sentences.push(describe_link(link));
+    }
+    sentences.push(
+        MASHUP_TEMPLATES
+            .choose(rng)
+            .unwrap_or(&MASHUP_TEMPLATES[0])
+            .replace("{title}", &mashed.title)
+            .replace("{artist}", &mashed.artist)
+            .replace("{album}", &mashed.album_title),
+    );
+    sentences.join(" ")
+}
@@ -0,0 +1,136 @@
+use crate::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const SAMPLE_SIZE: u32 = 64;
+
+/// Colors for theming a mashup card, as `#rrggbb`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Palette {
+    pub dominant: String,
+    pub vibrant: String,
+    pub muted: String,
+
+    /// Black or white, whichever reads better on `dominant`.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Swatch {
+    rgb: [f32; 3],
+    population: u32,
+}
+
+impl Swatch {
+    fn hex(&self) -> String {
+        let [r, g, b] = self.rgb.map(|c| c.round().clamp(0.0, 255.0) as u8);
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// Saturation and lightness, from 0 to 1.
+    fn saturation_lightness(&self) -> (f32, f32) {
+        let [r, g, b] = self.rgb.map(|c| c / 255.0);
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        (saturation, lightness)
+    }
+
+    /// WCAG relative luminance.
+    fn luminance(&self) -> f32 {
+        let [r, g, b] = self.rgb.map(|c| {
+            let c = c / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+}
+
+fn contrast(luminance1: f32, luminance2: f32) -> f32 {
+    let (lighter, darker) = (luminance1.max(luminance2), luminance1.min(luminance2));
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Buckets pixels by their top 4 bits per channel and averages each bucket.
+fn swatches(image: &DynamicImage) -> Vec<Swatch> {
+    let sample = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgb8();
+    let mut buckets: HashMap<[u8; 3], ([u32; 3], u32)> = HashMap::new();
+    for pixel in sample.pixels() {
+        let (sum, count) = buckets
+            .entry(pixel.0.map(|c| c >> 4))
+            .or_insert(([0; 3], 0));
+        for (total, c) in sum.iter_mut().zip(pixel.0) {
+            *total += c as u32;
+        }
+        *count += 1;
+    }
+    let mut swatches: Vec<Swatch> = buckets
+        .into_values()
+        .map(|(sum, population)| Swatch {
+            rgb: sum.map(|total| total as f32 / population as f32),
+            population,
+        })
+        .collect();
+    // Largest first, then by color so ties don't depend on hash order
+    swatches.sort_by(|a, b| b.population.cmp(&a.population).then(a.hex().cmp(&b.hex())));
+    swatches
+}
+
+/// The swatch best matching the wanted saturation and lightness, as scored by
+/// `score`, with more populous swatches favoured.
+fn best_swatch(swatches: &[Swatch], score: impl Fn(f32, f32) -> f32) -> Option<Swatch> {
+    swatches
+        .iter()
+        .map(|swatch| {
+            let (saturation, lightness) = swatch.saturation_lightness();
+            let score = score(saturation, lightness) * (swatch.population as f32).sqrt();
+            (swatch, score)
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(swatch, _)| *swatch)
+}
+
+/// Extracts a palette from a decoded cover image.
+pub fn extract(image: &DynamicImage) -> Result<Palette> {
+    let swatches = swatches(image);
+    let Some(dominant) = swatches.first().copied() else {
+        return Err("Cover has no pixels".into());
+    };
+    let vibrant = best_swatch(&swatches, |saturation, lightness| {
+        if saturation < 0.35 || !(0.3..=0.7).contains(&lightness) {
+            return 0.0;
+        }
+        saturation * (1.0 - (lightness - 0.5).abs() * 2.0)
+    })
+    .unwrap_or(dominant);
+    let muted = best_swatch(&swatches, |saturation, lightness| {
+        if saturation > 0.4 || !(0.25..=0.75).contains(&lightness) {
+            return 0.0;
+        }
+        1.0 - saturation
+    })
+    .unwrap_or(dominant);
+
+    let luminance = dominant.luminance();
+    let text = if contrast(luminance, 1.0) >= contrast(luminance, 0.0) {
+        "#ffffff"
+    } else {
+        "#000000"
+    };
+    Ok(Palette {
+        dominant: dominant.hex(),
+        vibrant: vibrant.hex(),
+        muted: muted.hex(),
+        text: text.into(),
+    })
+}
@@ -0,0 +1,354 @@
+use super::mash::{MashContext, NameMasher};
+use super::track::SeededRng;
+use rand::Rng;
+use std::cmp::Reverse;
+
+/// Consonant pairs that can start an English syllable, so they stay together
+/// when splitting a consonant cluster (as in "ap-ple" vs "mo-ther").
+const ONSETS: [&str; 30] = [
+    "bl", "br", "ch", "cl", "cr", "dr", "fl", "fr", "gl", "gn", "gr", "kn", "ph", "pl", "pr", "qu",
+    "sc", "sh", "sk", "sl", "sm", "sn", "sp", "st", "sw", "th", "tr", "tw", "wh", "wr",
+];
+
+/// How many of the best-scoring joins a non-deterministic masher picks from.
+const TOP_CANDIDATES: usize = 3;
+
+fn is_vowel(chars: &[char], i: usize) -> bool {
+    match chars[i].to_ascii_lowercase() {
+        'a' | 'e' | 'i' | 'o' | 'u' => true,
+        // "y" is a vowel unless it starts a word or follows a vowel ("yes", "day")
+        'y' => i > 0 && !is_vowel(chars, i - 1),
+        c => !c.is_ascii() && c.is_alphabetic() && "àáâäãåæèéêëìíîïòóôöõøœùúûüý".contains(c),
+    }
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphabetic() || (c.is_alphabetic() && (c as u32) < 0x250)
+}
+
+/// Splits a single word into syllables using the usual English hyphenation
+/// rules of thumb: vowel runs form nuclei, a lone consonant starts the next
+/// syllable, consonant clusters split before a valid onset, and a silent
+/// final "e" belongs to the syllable before it. Non-latin words are split
+/// into characters.
+pub fn syllabify(word: &str) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.is_empty() {
+        return Vec::new();
+    }
+    if !chars.iter().all(|c| is_latin(*c) || !c.is_alphabetic()) {
+        return chars.iter().map(|c| c.to_string()).collect();
+    }
+
+    // Start and end of each vowel run
+    let mut nuclei: Vec<(usize, usize)> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if is_vowel(&chars, i) {
+            let start = i;
+            while i < chars.len() && is_vowel(&chars, i) {
+                i += 1;
+            }
+            nuclei.push((start, i));
+        } else {
+            i += 1;
+        }
+    }
+
+    // Silent final "e", but not "-le" as in "apple"
+    let n = chars.len();
+    let mut final_le = false;
+    if nuclei.len() > 1 && nuclei.last() == Some(&(n - 1, n)) && chars[n - 1] == 'e' {
+        let before = chars[n - 2].to_ascii_lowercase();
+        // "ble" and "tle" are syllables, but the "lle" of "ville" is not
+        let consonant_le = before == 'l'
+            && n > 2
+            && !is_vowel(&chars, n - 3)
+            && !chars[n - 3].eq_ignore_ascii_case(&'l');
+        if consonant_le {
+            final_le = true;
+        } else {
+            nuclei.pop();
+        }
+    }
+    if nuclei.len() < 2 {
+        return vec![word.to_string()];
+    }
+
+    let mut breaks = Vec::new();
+    for pair in nuclei.windows(2) {
+        let (cluster_start, cluster_end) = (pair[0].1, pair[1].0);
+        let cluster: String = chars[cluster_start..cluster_end]
+            .iter()
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let split = match cluster.chars().count() {
+            0 | 1 => cluster_start,
+            // The consonant before a final "-le" goes with it: "lit-tle"
+            _ if final_le && pair[1].0 == n - 1 => cluster_end - 2,
+            len => {
+                let last_two: String = cluster.chars().skip(len - 2).collect();
+                if ONSETS.contains(&last_two.as_str()) {
+                    cluster_end - 2
+                } else {
+                    cluster_end - 1
+                }
+            }
+        };
+        breaks.push(split);
+    }
+
+    let mut syllables = Vec::new();
+    let mut start = 0;
+    for split in breaks.into_iter().chain([n]) {
+        if split > start {
+            syllables.push(chars[start..split].iter().collect());
+            start = split;
+        }
+    }
+    syllables
+}
+
+/// A name's syllables, marking which ones start a word.
+fn name_syllables(name: &str) -> Vec<(String, bool)> {
+    name.split_whitespace()
+        .flat_map(|word| {
+            syllabify(word)
+                .into_iter()
+                .enumerate()
+                .map(|(i, s)| (s, i == 0))
+        })
+        .collect()
+}
+
+fn join_syllables(syllables: &[(String, bool)]) -> String {
+    let mut result = String::new();
+    for (i, (syllable, word_start)) in syllables.iter().enumerate() {
+        if i > 0 && *word_start {
+            result.push(' ');
+        }
+        result.push_str(syllable);
+    }
+    result
+}
+
+fn ends_with_vowel(s: &str) -> bool {
+    let chars: Vec<char> = s.chars().collect();
+    !chars.is_empty() && is_vowel(&chars, chars.len() - 1)
+}
+
+fn starts_with_vowel(s: &str) -> bool {
+    let chars: Vec<char> = s.chars().collect();
+    !chars.is_empty() && is_vowel(&chars, 0)
+}
+
+struct Join {
+    score: i32,
+    name: String,
+}
+
+/// Scores joining the first `head` syllables of one name to the syllables of
+/// the other from `tail` onwards. Sharing a letter at the seam, alternating
+/// consonant and vowel across it, and a length close to the originals all
+/// make for a more pronounceable result.
+fn score_join(head: &[(String, bool)], tail: &[(String, bool)], target: usize) -> Join {
+    let head_text = join_syllables(head);
+    let mut tail_text = join_syllables(tail);
+
+    let mut score = 0;
+    let last = head_text.chars().last().map(|c| c.to_ascii_lowercase());
+    let first = tail_text.chars().next().map(|c| c.to_ascii_lowercase());
+    if last.is_some() && last == first {
+        // Overlap at the seam: "Madonna" + "Arcade Fire" -> "Marcade Fire"
+        score += 3;
+        tail_text = tail_text.chars().skip(1).collect();
+    } else if ends_with_vowel(&head_text) != starts_with_vowel(&tail_text) {
+        score += 2;
+    } else {
+        score -= 1;
+    }
+
+    let syllables = head.len() + tail.len();
+    score -= (syllables as i32 - target as i32).abs();
+    if !(2..=4).contains(&syllables) {
+        score -= 2;
+    }
+
+    // The tail continues a word unless it starts one
+    let continues_word = !tail.first().is_some_and(|(_, word_start)| *word_start);
+    if continues_word || !head_text.contains(' ') {
+        tail_text = lowercase_first(&tail_text);
+    }
+    Join {
+        score,
+        name: format!("{head_text}{tail_text}"),
+    }
+}
+
+fn lowercase_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Every way of joining a head of `name1` to a tail of `name2`, best first.
+fn joins(name1: &str, name2: &str) -> Vec<Join> {
+    let syllables1 = name_syllables(name1);
+    let syllables2 = name_syllables(name2);
+    if syllables1.is_empty() || syllables2.is_empty() {
+        return Vec::new();
+    }
+    let target = (syllables1.len() + syllables2.len()).div_ceil(2);
+
+    let mut joins = Vec::new();
+    for head in 1..=syllables1.len() {
+        for tail in 0..syllables2.len() {
+            // Both names whole isn't a portmanteau
+            if head == syllables1.len() && tail == 0 {
+                continue;
+            }
+            joins.push(score_join(&syllables1[..head], &syllables2[tail..], target));
+        }
+    }
+    // Stable, so ties keep the earliest split and the order is reproducible
+    joins.sort_by_key(|join| Reverse(join.score));
+    joins
+}
+
+/// Joins the head of one name to the tail of another at a syllable boundary,
+/// e.g. "Taylor Swift" + "Metallica" -> "Taytallica". The deterministic variant always
+/// puts the first name first and takes the best-scoring join, for tests and
+/// reproducible output independent of the RNG.
+pub struct SyllablePortmanteau {
+    pub deterministic: bool,
+}
+
+impl SyllablePortmanteau {
+    pub fn join(&self, name1: &str, name2: &str, rng: &mut SeededRng) -> Option<String> {
+        let (first, second) = if self.deterministic || rng.gen::<bool>() {
+            (name1, name2)
+        } else {
+            (name2, name1)
+        };
+        let mut joins = joins(first, second);
+        if joins.is_empty() {
+            return None;
+        }
+        let index = if self.deterministic {
+            0
+        } else {
+            rng.gen_range(0..joins.len().min(TOP_CANDIDATES))
+        };
+        Some(joins.swap_remove(index).name)
+    }
+}
+
+impl NameMasher for SyllablePortmanteau {
+    fn name(&self) -> &'static str {
+        if self.deterministic {
+            "syllables-fixed"
+        } else {
+            "syllables"
+        }
+    }
+
+    fn mash(
+        &self,
+        name1: &str,
+        name2: &str,
+        _context: &MashContext,
+        rng: &mut SeededRng,
+    ) -> String {
+        self.join(name1, name2, rng)
+            .unwrap_or_else(|| format!("{name1} {name2}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn syllable_boundaries() {
+        let cases: [(&str, &[&str]); 16] = [
+            ("apple", &["ap", "ple"]),
+            ("table", &["ta", "ble"]),
+            ("little", &["lit", "tle"]),
+            ("mother", &["mo", "ther"]),
+            ("monster", &["mon", "ster"]),
+            ("extra", &["ex", "tra"]),
+            ("yellow", &["yel", "low"]),
+            ("banana", &["ba", "na", "na"]),
+            ("make", &["make"]),
+            ("day", &["day"]),
+            ("Taylor", &["Tay", "lor"]),
+            ("Metallica", &["Me", "tal", "li", "ca"]),
+            ("Nashville", &["Nash", "ville"]),
+            ("Nirvana", &["Nir", "va", "na"]),
+            ("Beyoncé", &["Be", "yon", "cé"]),
+            ("東京", &["東", "京"]),
+        ];
+        for (word, syllables) in cases {
+            assert_eq!(syllabify(word), syllables, "{word}");
+        }
+        assert!(syllabify("").is_empty());
+    }
+
+    #[test]
+    fn deterministic_joins() {
+        let fixed = SyllablePortmanteau {
+            deterministic: true,
+        };
+        let cases = [
+            ("Taylor Swift", "Metallica", "Taytallica"),
+            ("Nirvana", "Nashville", "Nirvaville"),
+            ("Adele", "Eminem", "Aminem"),
+            ("Daft Punk", "Coldplay", "Daftplay"),
+            ("Madonna", "Arcade Fire", "Marcade Fire"),
+        ];
+        for (name1, name2, expected) in cases {
+            // The RNG must not matter
+            for seed in [0, 1, 42] {
+                let mut rng = SeededRng::seed_from_u64(seed);
+                assert_eq!(
+                    fixed.join(name1, name2, &mut rng).as_deref(),
+                    Some(expected),
+                    "{name1} + {name2}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_joins_are_reproducible() {
+        let masher = SyllablePortmanteau {
+            deterministic: false,
+        };
+        for seed in 0..20 {
+            let join = |seed| {
+                masher.join(
+                    "Taylor Swift",
+                    "Metallica",
+                    &mut SeededRng::seed_from_u64(seed),
+                )
+            };
+            assert_eq!(join(seed), join(seed));
+        }
+    }
+
+    #[test]
+    fn short_and_empty_names() {
+        let fixed = SyllablePortmanteau {
+            deterministic: true,
+        };
+        let mut rng = SeededRng::seed_from_u64(0);
+        assert_eq!(
+            fixed.join("Toto", "Abba", &mut rng).as_deref(),
+            Some("Toba")
+        );
+        assert_eq!(fixed.join("", "Abba", &mut rng), None);
+    }
+}
@@ -1,47 +1,70 @@
-use super::models::{MashedTrackAsset, TrackAsset, TrackOrigin};
-use crate::apis::{
-    base::{APIResult, Pagination},
-    deezer as d, dictionary as dict,
-};
+use super::artwork;
+use super::models::{TrackAsset, TrackOrigin};
+use super::palette::{self, Palette};
+use super::words::WordSource;
+use crate::apis::dictionary as dict;
+use crate::dictionaries::Dictionary;
+use crate::language::Language;
+use crate::providers::{IndexedTrack, MusicProvider, Track};
 use crate::{Error, Result};
-use log::{debug, error, info};
-use rand::{random, Rng};
-use random_word::{gen_starts_with, Lang};
+use base64::{engine::general_purpose, Engine as _};
+use bytes::Bytes;
+use log::{debug, error, info, warn};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
 use tokio::time::{sleep, Duration};
 
-fn random_word() -> String {
-    let mut rng = rand::thread_rng();
-    loop {
-        let letter = rng.gen_range(97..=122) as u8 as char;
-        if let Some(query) = gen_starts_with(letter, Lang::En) {
-            return query.to_owned();
-        }
-    }
+/// The RNG threaded through mashup generation. ChaCha is used rather than
+/// `StdRng` because its output is stable across `rand` releases, so a stored
+/// seed keeps reproducing the same mashup.
+pub type SeededRng = ChaCha8Rng;
+
+/// Everything a mashup's tracks are drawn from.
+pub struct Sources<'a> {
+    pub provider: &'a dyn MusicProvider,
+    pub dictionary: &'a dyn Dictionary,
+    pub words: &'a dyn WordSource,
+    pub language: Language,
 }
 
-struct TrackSearch {
-    word: String,
-    result: APIResult<d::DeezerPaginationResponse<d::TrackList>>,
+pub struct TrackSearch {
+    pub word: String,
+    pub total: u64,
 }
 
-async fn random_track_search() -> Result<TrackSearch> {
+async fn random_track_search(
+    provider: &dyn MusicProvider,
+    words: &dyn WordSource,
+    rng: &mut SeededRng,
+) -> Result<TrackSearch> {
     info!("Conducting random track search...");
     let mut attempt = 1;
     let num_retries = 10;
+    let mut empty_searches = 0;
+    let max_empty_searches = 50;
 
     loop {
         debug!("Attempt {}...", attempt);
-        let word = random_word();
+        let word = words
+            .word(rng)
+            .ok_or(Error::Custom(format!("No words in '{}'", words.name())))?;
         debug!("Querying '{}'", word);
-        match d::search_tracks(&word).await {
-            Ok(result) => {
-                if result.response.total > 0 {
-                    info!(
-                        "Found {} results for query '{}'",
-                        result.response.total, word
-                    );
-                    return Ok(TrackSearch { word, result });
+        match provider.search(&word).await {
+            Ok(total) => {
+                if total > 0 {
+                    info!("Found {} results for query '{}'", total, word);
+                    return Ok(TrackSearch { word, total });
                 }
+                // Searches may not yield, e.g. for the local provider
+                empty_searches += 1;
+                if empty_searches >= max_empty_searches {
+                    return Err(Error::CriticalError(format!(
+                        "No results for {} words from '{}'",
+                        empty_searches,
+                        words.name()
+                    )));
+                }
+                tokio::task::yield_now().await;
             }
             Err(err) => {
                 error!("{}", err);
@@ -58,126 +81,125 @@ async fn random_track_search() -> Result<TrackSearch> {
     }
 }
 
-fn find_index_with_preview(tracks: &d::TrackList, start_index: &usize) -> Result<usize> {
-    if *start_index >= tracks.len() {
-        return Err(Error::IndexError {
-            index: start_index.to_owned(),
-            length: tracks.len(),
-        });
-    }
-    for (i, track) in tracks[*start_index..].iter().enumerate() {
-        if !track.preview_url.is_empty() {
-            debug!(
-                "Found track '{}' positions from start index '{}'",
-                i, start_index
-            );
-            return Ok(i);
+async fn pick_random_track(
+    provider: &dyn MusicProvider,
+    track_search: &TrackSearch,
+    rng: &mut SeededRng,
+) -> Result<IndexedTrack> {
+    let index = rng.gen_range(0..track_search.total);
+    provider.track_at(&track_search.word, index).await
+}
+
+async fn lookup_dictionary_entry(
+    dictionary: &dyn Dictionary,
+    word: &str,
+    language: Language,
+) -> dict::Word {
+    match dictionary.lookup(word, language).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => dict::Word::unknown(word.to_string()),
+        Err(err) => {
+            warn!("Dictionary lookup of '{}' failed: {}", word, err);
+            dict::Word::unknown(word.to_string())
         }
     }
-    Err(Error::CriticalError("No track with preview".into()))
 }
 
-struct RandomTrack {
-    index: u64,
-    track: d::Track,
+async fn fetch_pronunciation(word: &dict::Word) -> Option<String> {
+    let url = word.pronunciation_url()?;
+    match dict::download(&url).await {
+        Ok(audio) => Some(general_purpose::STANDARD.encode(audio)),
+        Err(err) => {
+            warn!("Failed to fetch pronunciation for '{}': {}", word.word, err);
+            None
+        }
+    }
 }
 
-async fn pick_random_track(track_search: &TrackSearch) -> Result<RandomTrack> {
-    let mut rng = rand::thread_rng();
-    let index = rng.gen_range(0..track_search.result.response.total);
-    let mut page = track_search.result.get_page_from_index(&index).await?;
-    let start_index_of_page = index % track_search.result.response.page_limit();
-    let index_on_page =
-        find_index_with_preview(&page.response.data, &(start_index_of_page as usize))?;
-
-    if index_on_page >= page.response.data.len() {
-        return Err(Error::IndexError {
-            index: index_on_page,
-            length: page.response.data.len(),
-        });
-    }
+/// A track asset along with its cover, downloaded once and reused for the
+/// mashed cover.
+pub struct BuiltTrack {
+    pub asset: TrackAsset,
+    pub cover: Option<Bytes>,
+}
 
-    let track = page.response.data.swap_remove(index_on_page);
-    let true_index = index + index_on_page as u64 - start_index_of_page;
+async fn fetch_cover(provider: &dyn MusicProvider, track: &Track) -> Option<Bytes> {
+    provider
+        .cover(track)
+        .await
+        .inspect_err(|err| warn!("Failed to fetch cover for '{}': {}", track.title, err))
+        .ok()
+}
 
-    Ok(RandomTrack {
-        index: true_index,
-        track,
+/// Placeholder and theme colors for the track's cover, from a single decode
+/// off the async executor. Both are cosmetic, so an undecodable cover leaves
+/// them empty.
+async fn cover_artwork(track: &Track, cover: Bytes) -> (Option<String>, Option<Palette>) {
+    let title = track.title.clone();
+    let artwork = tokio::task::spawn_blocking(move || {
+        let image = match image::load_from_memory(&cover) {
+            Ok(image) => image,
+            Err(err) => {
+                warn!("Failed to decode cover of '{}': {}", title, err);
+                return (None, None);
+            }
+        };
+        let blurhash = artwork::blurhash(&image)
+            .inspect_err(|err| warn!("Failed to compute blurhash for '{}': {}", title, err))
+            .ok();
+        let palette = palette::extract(&image)
+            .inspect_err(|err| warn!("Failed to extract palette for '{}': {}", title, err))
+            .ok();
+        (blurhash, palette)
+    })
+    .await;
+    artwork.unwrap_or_else(|err| {
+        warn!("Cover artwork task failed for '{}': {}", track.title, err);
+        (None, None)
     })
 }
 
-async fn lookup_dictionary_entry(word: &str) -> dict::Word {
-    if let Ok(mut res) = dict::search_dictionary(word).await {
-        if !res.response.is_empty() {
-            return res.response.swap_remove(0);
-        }
-    }
-    dict::Word::unknown(word.to_string())
+pub async fn build_track_asset(sources: &Sources<'_>, rng: &mut SeededRng) -> Result<BuiltTrack> {
+    let track_search = random_track_search(sources.provider, sources.words, rng).await?;
+    build_track_asset_from_search(sources, track_search, rng).await
 }
 
-pub async fn build_track_asset() -> Result<TrackAsset> {
-    let track_search = random_track_search().await?;
-    let total_tracks = track_search.result.response.total;
-    let word = lookup_dictionary_entry(&track_search.word).await;
-    let random_track = pick_random_track(&track_search).await?;
-    let preview = d::encoded_preview(&random_track.track.preview_url).await?;
-
-    Ok(TrackAsset::from_track(
+pub async fn build_track_asset_from_search(
+    sources: &Sources<'_>,
+    track_search: TrackSearch,
+    rng: &mut SeededRng,
+) -> Result<BuiltTrack> {
+    let Sources {
+        provider,
+        dictionary,
+        words,
+        language,
+    } = *sources;
+    let total_tracks = track_search.total;
+    let word = lookup_dictionary_entry(dictionary, &track_search.word, language).await;
+    let pronunciation = fetch_pronunciation(&word).await;
+    let random_track = pick_random_track(provider, &track_search, rng).await?;
+    let preview = general_purpose::STANDARD.encode(provider.preview(&random_track.track).await?);
+    let cover = fetch_cover(provider, &random_track.track).await;
+    let (blurhash, palette) = match &cover {
+        Some(cover) => cover_artwork(&random_track.track, cover.clone()).await,
+        None => (None, None),
+    };
+
+    let mut asset = TrackAsset::from_track(
+        provider,
         random_track.track,
         preview,
         TrackOrigin {
             word,
+            pronunciation,
+            word_source: words.name(),
+            language,
             total_tracks,
             track_index: random_track.index,
         },
-    ))
-}
-
-fn combine_alternating_words(string1: &str, string2: &str) -> String {
-    let (words1, words2): (Vec<&str>, Vec<&str>);
-
-    if random::<bool>() {
-        words1 = string1.split_whitespace().collect();
-        words2 = string2.split_whitespace().collect();
-    } else {
-        words1 = string2.split_whitespace().collect();
-        words2 = string1.split_whitespace().collect();
-    }
-
-    let length_limit: u8 = 30;
-    let mut result_length: u8 = 0;
-
-    let mut result = Vec::new();
-    let len = words1.len().max(words2.len());
-
-    for i in 0..len {
-        if i < words1.len() {
-            let word = words1[i];
-            result.push(word);
-            result_length += word.len() as u8;
-            if result_length > length_limit {
-                break;
-            }
-        }
-        if i < words2.len() {
-            let word = words2[i];
-            result.push(word);
-            result_length += word.len() as u8;
-            if result_length > length_limit {
-                break;
-            }
-        }
-    }
-    result.join(" ")
-}
-
-pub fn mash_track_assets(track1: &TrackAsset, track2: &TrackAsset) -> MashedTrackAsset {
-    let title = combine_alternating_words(&track1.title, &track2.title);
-    let artist = combine_alternating_words(&track1.artist, &track2.artist);
-    let album_title = combine_alternating_words(&track1.album_title, &track2.album_title);
-    MashedTrackAsset {
-        title,
-        artist,
-        album_title,
-    }
+    );
+    asset.blurhash = blurhash;
+    asset.palette = palette;
+    Ok(BuiltTrack { asset, cover })
 }
@@ -0,0 +1,214 @@
+use super::track::SeededRng;
+use crate::{config, language::Language, providers::MusicProvider, Error, Result};
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
+use random_word::{all, all_starts_with};
+use std::{fs, path::Path, sync::Arc};
+
+#[rustfmt::skip]
+const COLORS: &[&str] = &[
+    "red", "orange", "yellow", "green", "blue", "purple", "pink", "black", "white", "gray",
+    "brown", "gold", "silver", "violet", "indigo", "scarlet", "crimson", "amber", "ivory",
+    "teal", "turquoise", "lavender", "magenta", "cyan", "maroon", "navy", "olive", "coral",
+    "emerald", "ruby", "sapphire", "jade", "cherry", "peach", "rose", "copper",
+];
+
+#[rustfmt::skip]
+const ANIMALS: &[&str] = &[
+    "dog", "cat", "horse", "tiger", "lion", "wolf", "fox", "bear", "eagle", "hawk", "raven",
+    "crow", "dove", "owl", "shark", "whale", "dolphin", "snake", "spider", "monkey", "rabbit",
+    "deer", "elephant", "zebra", "panther", "butterfly", "bee", "bird", "fish", "pony",
+    "tortoise", "kitten", "puppy", "bull", "buffalo", "camel", "falcon", "swan", "penguin",
+];
+
+/// Supplies the seed words that tracks are searched for.
+pub trait WordSource: Send + Sync {
+    /// Identifies the source on `TrackOrigin`.
+    fn name(&self) -> String;
+
+    fn word(&self, rng: &mut SeededRng) -> Option<String>;
+}
+
+/// The per-language lists built into `random_word`.
+pub struct BuiltinWords(pub Language);
+
+impl WordSource for BuiltinWords {
+    fn name(&self) -> String {
+        "builtin".into()
+    }
+
+    fn word(&self, rng: &mut SeededRng) -> Option<String> {
+        let list = self.0.word_list();
+        // Japanese and Chinese words don't start with a latin letter
+        if !self.0.is_spaced() {
+            return all(list).choose(rng).map(|w| w.to_string());
+        }
+        loop {
+            let letter = rng.gen_range(97..=122) as u8 as char;
+            if let Some(word) = all_starts_with(letter, list).and_then(|w| w.choose(rng)) {
+                return Some(word.to_string());
+            }
+        }
+    }
+}
+
+/// Names a list file by its file name, so origins can tell lists apart
+/// without exposing server paths.
+fn list_name(kind: &str, path: &str) -> String {
+    let file = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or(path.into());
+    format!("{kind}:{file}")
+}
+
+/// A user-supplied list with one word per line.
+pub struct FileWords {
+    name: String,
+    words: Vec<String>,
+}
+
+impl FileWords {
+    pub fn load(path: &str) -> Result<Self> {
+        let words = fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        Ok(Self {
+            name: list_name("file", path),
+            words,
+        })
+    }
+}
+
+impl WordSource for FileWords {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn word(&self, rng: &mut SeededRng) -> Option<String> {
+        self.words.choose(rng).cloned()
+    }
+}
+
+/// A frequency list with `word count` per line, so common words come up more
+/// often. Lines without a count get a weight of 1.
+pub struct WeightedWords {
+    name: String,
+    words: Vec<String>,
+    weights: Option<WeightedIndex<u64>>,
+}
+
+impl WeightedWords {
+    pub fn load(path: &str) -> Result<Self> {
+        let (words, weights): (Vec<String>, Vec<u64>) = fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let word = parts.next()?.to_string();
+                let weight = parts.next().and_then(|w| w.parse().ok()).unwrap_or(1);
+                Some((word, weight))
+            })
+            .unzip();
+        Ok(Self {
+            name: list_name("weighted", path),
+            weights: WeightedIndex::new(&weights).ok(),
+            words,
+        })
+    }
+}
+
+impl WordSource for WeightedWords {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn word(&self, rng: &mut SeededRng) -> Option<String> {
+        let index = self.weights.as_ref()?.sample(rng);
+        self.words.get(index).cloned()
+    }
+}
+
+/// A small built-in list of words on one theme.
+pub struct ThemedWords {
+    theme: String,
+    words: &'static [&'static str],
+}
+
+impl ThemedWords {
+    pub fn new(theme: &str) -> Result<Self> {
+        let words = match theme {
+            "colors" => COLORS,
+            "animals" => ANIMALS,
+            other => return Err(Error::Custom(format!("Unknown word theme '{other}'"))),
+        };
+        Ok(Self {
+            theme: theme.to_string(),
+            words,
+        })
+    }
+}
+
+impl WordSource for ThemedWords {
+    fn name(&self) -> String {
+        format!("themed:{}", self.theme)
+    }
+
+    fn word(&self, rng: &mut SeededRng) -> Option<String> {
+        self.words.choose(rng).map(|w| w.to_string())
+    }
+}
+
+/// The words a provider can match, e.g. every word in a local library's
+/// tags, so every search finds something.
+pub struct LibraryWords {
+    words: Arc<[String]>,
+}
+
+impl WordSource for LibraryWords {
+    fn name(&self) -> String {
+        "library".into()
+    }
+
+    fn word(&self, rng: &mut SeededRng) -> Option<String> {
+        self.words.choose(rng).cloned()
+    }
+}
+
+/// Builds a word source from a spec such as `builtin`, `file`, `weighted`,
+/// `library` or `themed:animals`. List files are configured server-side,
+/// never per request. Only the built-in lists vary by language.
+pub fn from_spec(
+    spec: &str,
+    language: Language,
+    provider: &dyn MusicProvider,
+) -> Result<Box<dyn WordSource>> {
+    let config = config::get()?;
+    let required = |path: &Option<String>, var: &str| {
+        path.clone().ok_or(Error::Custom(format!(
+            "{var} is required for '{spec}' words"
+        )))
+    };
+    match spec.split_once(':') {
+        Some(("themed", theme)) => Ok(Box::new(ThemedWords::new(theme)?)),
+        None if spec == "builtin" => Ok(Box::new(BuiltinWords(language))),
+        None if spec == "library" => match provider.vocabulary() {
+            Some(words) => Ok(Box::new(LibraryWords { words })),
+            None => Err(Error::Custom(format!(
+                "The {} provider has no word library",
+                provider.name()
+            ))),
+        },
+        None if spec == "file" => Ok(Box::new(FileWords::load(&required(
+            &config.word_list_file,
+            "WORD_LIST_FILE",
+        )?)?)),
+        None if spec == "weighted" => Ok(Box::new(WeightedWords::load(&required(
+            &config.word_frequency_file,
+            "WORD_FREQUENCY_FILE",
+        )?)?)),
+        _ => Err(Error::Custom(format!("Unknown word source '{spec}'"))),
+    }
+}
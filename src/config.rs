@@ -0,0 +1,123 @@
+use crate::{language::Language, Error, Result};
+use chrono::NaiveDate;
+use std::{env, str::FromStr, sync::OnceLock};
+
+const DEEZER_URL: &str = "https://api.deezer.com";
+const DICTIONARY_URL: &str = "https://api.dictionaryapi.dev/api/v2";
+const DAILY_EPOCH: &str = "2024-11-01";
+const NAME_MASHERS: &str = "alternating:4,splice:2,portmanteau:2,interleaved:1,template:1";
+const ARTIST_MASHERS: &str = "syllables:3,alternating:1";
+const NAME_LIMIT: usize = 30;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Base URLs for every upstream API, plus settings for how mashups are
+/// generated. URLs can be overridden from the environment to point the
+/// service at stand-in servers, mirrors or proxies.
+#[derive(Debug)]
+pub struct Config {
+    pub music_provider: String,
+    pub local_library_dir: Option<String>,
+    pub deezer_url: String,
+    pub dictionary_url: String,
+    pub supabase_url: String,
+
+    /// Comma-separated dictionaries to try in order, e.g. `remote,wordnet`.
+    pub dictionary_backend: String,
+    pub wordnet_dir: Option<String>,
+
+    /// Word source and language used when a refresh doesn't ask for one.
+    pub word_source: String,
+    pub language: Language,
+    pub word_list_file: Option<String>,
+    pub word_frequency_file: Option<String>,
+
+    /// Weighted name masher strategies, see `MasherSet::from_spec`.
+    pub name_mashers: String,
+    pub artist_mashers: String,
+
+    /// Sampling temperature for the "markov" title strategy.
+    pub markov_temperature: f64,
+
+    /// Length budgets for mashed fields, in graphemes or display columns.
+    pub title_limit: usize,
+    pub artist_limit: usize,
+    pub album_title_limit: usize,
+    pub limit_by_width: bool,
+
+    /// Font for the title on mashed covers. Covers are untitled without one.
+    pub cover_font_file: Option<String>,
+
+    /// Server secret mixed into daily mashup seeds so they can't be predicted.
+    pub daily_secret: Option<String>,
+
+    /// Date of daily puzzle #1.
+    pub daily_epoch: NaiveDate,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        let music_provider = env::var("MUSIC_PROVIDER").unwrap_or("deezer".into());
+        // Dictionary words rarely match a local library's tags
+        let word_source = match music_provider.as_str() {
+            "local" => "library",
+            _ => "builtin",
+        };
+        Ok(Self {
+            music_provider,
+            local_library_dir: env::var("LOCAL_LIBRARY_DIR").ok(),
+            deezer_url: base_url(env::var("DEEZER_URL").unwrap_or(DEEZER_URL.into())),
+            dictionary_url: base_url(env::var("DICTIONARY_URL").unwrap_or(DICTIONARY_URL.into())),
+            supabase_url: base_url(env::var("SUPABASE_URL")?),
+            dictionary_backend: env::var("DICTIONARY_BACKEND").unwrap_or("remote".into()),
+            wordnet_dir: env::var("WORDNET_DIR").ok(),
+            word_source: env::var("WORD_SOURCE").unwrap_or(word_source.into()),
+            language: match env::var("LANGUAGE") {
+                Ok(code) => Language::from_code(&code)
+                    .ok_or(Error::Custom(format!("Unknown LANGUAGE '{code}'")))?,
+                Err(_) => Language::default(),
+            },
+            word_list_file: env::var("WORD_LIST_FILE").ok(),
+            word_frequency_file: env::var("WORD_FREQUENCY_FILE").ok(),
+            name_mashers: env::var("NAME_MASHERS").unwrap_or(NAME_MASHERS.into()),
+            artist_mashers: env::var("ARTIST_MASHERS").unwrap_or(ARTIST_MASHERS.into()),
+            markov_temperature: parse_var("MARKOV_TEMPERATURE", 1.0)?,
+            title_limit: parse_var("TITLE_LIMIT", NAME_LIMIT)?,
+            artist_limit: parse_var("ARTIST_LIMIT", NAME_LIMIT)?,
+            album_title_limit: parse_var("ALBUM_TITLE_LIMIT", NAME_LIMIT)?,
+            limit_by_width: parse_var("LIMIT_BY_WIDTH", false)?,
+            cover_font_file: env::var("COVER_FONT_FILE").ok(),
+            daily_secret: env::var("DAILY_SECRET").ok(),
+            daily_epoch: NaiveDate::parse_from_str(
+                &env::var("DAILY_EPOCH").unwrap_or(DAILY_EPOCH.into()),
+                "%Y-%m-%d",
+            )
+            .map_err(|e| Error::Custom(format!("Invalid DAILY_EPOCH: {e}")))?,
+        })
+    }
+}
+
+fn parse_var<T: FromStr>(key: &str, default: T) -> Result<T> {
+    match env::var(key) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| Error::Custom(format!("Invalid {key} '{value}'"))),
+        Err(_) => Ok(default),
+    }
+}
+
+fn base_url(url: String) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Loads the configuration from the environment. Must be called once at startup.
+pub fn init() -> Result<&'static Config> {
+    let config = Config::from_env()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+pub fn get() -> Result<&'static Config> {
+    CONFIG.get().ok_or(Error::CriticalError(
+        "Configuration was not initialized".into(),
+    ))
+}
@@ -0,0 +1,62 @@
+mod remote;
+mod wordnet;
+
+pub use remote::RemoteDictionary;
+pub use wordnet::WordNetDictionary;
+
+use crate::{apis::dictionary::Word, config::Config, language::Language, Error, Result};
+use async_trait::async_trait;
+use log::warn;
+use std::{path::Path, sync::Arc};
+
+#[async_trait]
+pub trait Dictionary: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Looks up `word`, returning `None` if the dictionary has no entry.
+    async fn lookup(&self, word: &str, language: Language) -> Result<Option<Word>>;
+}
+
+/// Tries each dictionary in turn until one has an entry, so a local backend
+/// can be used as the primary or as a fallback for the remote one.
+pub struct DictionaryChain {
+    dictionaries: Vec<Box<dyn Dictionary>>,
+}
+
+#[async_trait]
+impl Dictionary for DictionaryChain {
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
+    async fn lookup(&self, word: &str, language: Language) -> Result<Option<Word>> {
+        for dictionary in &self.dictionaries {
+            match dictionary.lookup(word, language).await {
+                Ok(Some(entry)) => return Ok(Some(entry)),
+                Ok(None) => {}
+                Err(err) => warn!("{} lookup of '{}' failed: {}", dictionary.name(), word, err),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Builds the dictionaries listed in `DICTIONARY_BACKEND`, e.g. `remote,wordnet`.
+pub fn from_config(config: &Config) -> Result<Arc<dyn Dictionary>> {
+    let mut dictionaries: Vec<Box<dyn Dictionary>> = Vec::new();
+    for backend in config.dictionary_backend.split(',').map(str::trim) {
+        match backend {
+            "remote" => dictionaries.push(Box::new(RemoteDictionary)),
+            "wordnet" => match &config.wordnet_dir {
+                Some(dir) => dictionaries.push(Box::new(WordNetDictionary::load(Path::new(dir))?)),
+                None => {
+                    return Err(Error::Custom(
+                        "WORDNET_DIR is required for the wordnet dictionary".into(),
+                    ))
+                }
+            },
+            other => return Err(Error::Custom(format!("Unknown dictionary '{other}'"))),
+        }
+    }
+    Ok(Arc::new(DictionaryChain { dictionaries }))
+}
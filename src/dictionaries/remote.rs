@@ -0,0 +1,25 @@
+use super::Dictionary;
+use crate::apis::dictionary::{self as dict, Word};
+use crate::{language::Language, Error, Result};
+use async_trait::async_trait;
+
+/// The HTTP dictionary API.
+pub struct RemoteDictionary;
+
+#[async_trait]
+impl Dictionary for RemoteDictionary {
+    fn name(&self) -> &'static str {
+        "remote"
+    }
+
+    async fn lookup(&self, word: &str, language: Language) -> Result<Option<Word>> {
+        match dict::search_dictionary(word, language).await {
+            Ok(mut res) if !res.response.is_empty() => Ok(Some(res.response.swap_remove(0))),
+            Ok(_) => Ok(None),
+            Err(Error::ResponseError {
+                status_code: 404, ..
+            }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
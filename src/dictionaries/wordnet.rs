@@ -0,0 +1,221 @@
+use super::Dictionary;
+use crate::apis::dictionary::{Definition, Meaning, Word};
+use crate::{language::Language, Result};
+use async_trait::async_trait;
+use log::info;
+use std::{collections::HashMap, fs, path::Path};
+
+const DATA_FILES: [&str; 4] = ["data.noun", "data.verb", "data.adj", "data.adv"];
+
+struct Pointer {
+    pos: char,
+    offset: u64,
+    target_word: usize,
+}
+
+struct Synset {
+    pos: char,
+    words: Vec<String>,
+    antonyms: Vec<Pointer>,
+    definition: String,
+    example: Option<String>,
+}
+
+/// An in-process dictionary loaded from the `data.*` files of a WordNet
+/// database directory. WordNet is English only.
+pub struct WordNetDictionary {
+    synsets: HashMap<(char, u64), Synset>,
+    lemmas: HashMap<String, Vec<(char, u64)>>,
+}
+
+/// Satellite adjectives are stored as `s` but are plain adjectives to a reader.
+fn normalize_pos(pos: char) -> char {
+    if pos == 's' {
+        'a'
+    } else {
+        pos
+    }
+}
+
+fn part_of_speech(pos: char) -> &'static str {
+    match pos {
+        'n' => "noun",
+        'v' => "verb",
+        'a' => "adjective",
+        _ => "adverb",
+    }
+}
+
+/// Lemmas use underscores for spaces, and adjectives may carry a syntactic
+/// marker such as `(p)`.
+fn clean_lemma(lemma: &str) -> String {
+    let lemma = lemma.split('(').next().unwrap_or(lemma);
+    lemma.replace('_', " ")
+}
+
+/// Splits a gloss like `a greeting; "hello, how are you?"` into its definition
+/// and first example.
+fn parse_gloss(gloss: &str) -> (String, Option<String>) {
+    let mut parts = gloss.split("; \"");
+    let definition = parts.next().unwrap_or_default().trim().to_string();
+    let example = parts
+        .next()
+        .map(|e| e.trim().trim_end_matches(['"', ';']).to_string());
+    (definition, example)
+}
+
+/// Parses one synset line:
+/// `offset lex_filenum ss_type w_cnt [word lex_id]... p_cnt [ptr]... | gloss`
+fn parse_synset(line: &str) -> Option<(u64, Synset)> {
+    let (fields, gloss) = line.split_once(" | ")?;
+    let mut tokens = fields.split_whitespace();
+    let offset = tokens.next()?.parse().ok()?;
+    let pos = normalize_pos(tokens.nth(1)?.chars().next()?);
+    let word_count = usize::from_str_radix(tokens.next()?, 16).ok()?;
+
+    let mut words = Vec::with_capacity(word_count);
+    for _ in 0..word_count {
+        words.push(clean_lemma(tokens.next()?));
+        tokens.next()?;
+    }
+
+    let pointer_count: usize = tokens.next()?.parse().ok()?;
+    let mut antonyms = Vec::new();
+    for _ in 0..pointer_count {
+        let symbol = tokens.next()?;
+        let target_offset = tokens.next()?.parse().ok()?;
+        let target_pos = normalize_pos(tokens.next()?.chars().next()?);
+        let source_target = tokens.next()?;
+        if symbol == "!" {
+            antonyms.push(Pointer {
+                pos: target_pos,
+                offset: target_offset,
+                target_word: usize::from_str_radix(source_target.get(2..)?, 16).ok()?,
+            });
+        }
+    }
+
+    let (definition, example) = parse_gloss(gloss);
+    Some((
+        offset,
+        Synset {
+            pos,
+            words,
+            antonyms,
+            definition,
+            example,
+        },
+    ))
+}
+
+impl WordNetDictionary {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut synsets = HashMap::new();
+        let mut lemmas: HashMap<String, Vec<(char, u64)>> = HashMap::new();
+
+        for file in DATA_FILES {
+            let data = fs::read_to_string(dir.join(file))?;
+            // The license header lines are indented
+            for line in data.lines().filter(|l| !l.starts_with(' ')) {
+                if let Some((offset, synset)) = parse_synset(line) {
+                    let key = (synset.pos, offset);
+                    for word in &synset.words {
+                        lemmas.entry(word.to_lowercase()).or_default().push(key);
+                    }
+                    synsets.insert(key, synset);
+                }
+            }
+        }
+        info!(
+            "Loaded {} WordNet synsets for {} lemmas",
+            synsets.len(),
+            lemmas.len()
+        );
+        Ok(Self { synsets, lemmas })
+    }
+
+    fn antonyms(&self, synset: &Synset) -> Vec<String> {
+        synset
+            .antonyms
+            .iter()
+            .filter_map(|pointer| {
+                let target = self.synsets.get(&(pointer.pos, pointer.offset))?;
+                match pointer.target_word {
+                    0 => target.words.first(),
+                    i => target.words.get(i - 1),
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn entry(&self, word: &str) -> Option<Word> {
+        let keys = self.lemmas.get(&word.to_lowercase())?;
+        let mut meanings: Vec<Meaning> = Vec::new();
+
+        for key in keys {
+            let Some(synset) = self.synsets.get(key) else {
+                continue;
+            };
+            let synonyms: Vec<String> = synset
+                .words
+                .iter()
+                .filter(|w| !w.eq_ignore_ascii_case(word))
+                .cloned()
+                .collect();
+            let antonyms = self.antonyms(synset);
+            let definition = Definition {
+                definition: synset.definition.clone(),
+                example: synset.example.clone(),
+                synonyms: synonyms.clone(),
+                antonyms: antonyms.clone(),
+            };
+
+            let part_of_speech = part_of_speech(synset.pos);
+            let meaning = match meanings
+                .iter_mut()
+                .find(|m| m.part_of_speech == part_of_speech)
+            {
+                Some(meaning) => meaning,
+                None => {
+                    meanings.push(Meaning {
+                        part_of_speech: part_of_speech.to_string(),
+                        definitions: Vec::new(),
+                        synonyms: Vec::new(),
+                        antonyms: Vec::new(),
+                    });
+                    meanings.last_mut()?
+                }
+            };
+            meaning.definitions.push(definition);
+            for synonym in synonyms {
+                if !meaning.synonyms.contains(&synonym) {
+                    meaning.synonyms.push(synonym);
+                }
+            }
+            for antonym in antonyms {
+                if !meaning.antonyms.contains(&antonym) {
+                    meaning.antonyms.push(antonym);
+                }
+            }
+        }
+
+        let mut entry = Word::unknown(word.to_string());
+        entry.meanings = Some(meanings);
+        Some(entry)
+    }
+}
+
+#[async_trait]
+impl Dictionary for WordNetDictionary {
+    fn name(&self) -> &'static str {
+        "wordnet"
+    }
+
+    async fn lookup(&self, word: &str, language: Language) -> Result<Option<Word>> {
+        if language != Language::En {
+            return Ok(None);
+        }
+        Ok(self.entry(word))
+    }
+}
@@ -30,6 +30,15 @@ pub enum Error {
 
     #[from]
     RedisError(redis::RedisError),
+
+    #[from]
+    IoError(std::io::Error),
+
+    #[from]
+    AudioError(symphonia::core::errors::Error),
+
+    #[from]
+    ImageError(image::ImageError),
 }
 
 impl Error {
@@ -46,7 +55,7 @@ impl From<&str> for Error {
 
 impl From<Error> for std::io::Error {
     fn from(e: Error) -> Self {
-        std::io::Error::new(std::io::ErrorKind::Other, e)
+        std::io::Error::other(e)
     }
 }
 
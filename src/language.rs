@@ -0,0 +1,58 @@
+use random_word::Lang;
+use serde::{Deserialize, Serialize};
+
+/// Languages a mashup can be generated in. Selects the seed word list, the
+/// dictionary endpoint and how titles are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Es,
+    Fr,
+    Ja,
+    Zh,
+}
+
+impl Language {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.to_lowercase().as_str() {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            "es" => Some(Self::Es),
+            "fr" => Some(Self::Fr),
+            "ja" => Some(Self::Ja),
+            "zh" => Some(Self::Zh),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Es => "es",
+            Self::Fr => "fr",
+            Self::Ja => "ja",
+            Self::Zh => "zh",
+        }
+    }
+
+    pub fn word_list(&self) -> Lang {
+        match self {
+            Self::En => Lang::En,
+            Self::De => Lang::De,
+            Self::Es => Lang::Es,
+            Self::Fr => Lang::Fr,
+            Self::Ja => Lang::Ja,
+            Self::Zh => Lang::Zh,
+        }
+    }
+
+    /// Whether words are separated by spaces. Japanese and Chinese titles
+    /// are written without them.
+    pub fn is_spaced(&self) -> bool {
+        !matches!(self, Self::Ja | Self::Zh)
+    }
+}
@@ -1,6 +1,10 @@
 mod apis;
 mod assets;
+mod config;
+mod dictionaries;
 mod error;
+mod language;
+mod providers;
 
 pub use self::error::{Error, Result};
 
@@ -13,7 +17,10 @@ use log::{error, info};
 use redis::Client;
 use std::{env, sync::Arc};
 
-use assets::manager;
+use assets::{artwork, cover, daily, manager};
+use chrono::NaiveDate;
+use dictionaries::Dictionary;
+use providers::MusicProvider;
 
 async fn get_redis_connection() -> Result<Client> {
     let instance = env::var("UPSTASH_INSTANCE")?;
@@ -25,8 +32,20 @@ async fn get_redis_connection() -> Result<Client> {
 }
 
 #[get("/retrieve-assets")]
-async fn retrieve_assets(redis_client: web::Data<Arc<Client>>) -> ActixResult<impl Responder> {
+async fn retrieve_assets(
+    redis_client: web::Data<Arc<Client>>,
+    query: web::Query<manager::SummaryQuery>,
+) -> ActixResult<impl Responder> {
     info!("retrieving assets...");
+    if query.summary {
+        return match manager::retrieve_summaries(&redis_client).await {
+            Ok(summaries) => Ok(HttpResponse::Ok().json(summaries)),
+            Err(e) => {
+                error!("Error retrieving asset summaries: {e}");
+                Ok(HttpResponse::InternalServerError().json("Encountered error retrieving assets"))
+            }
+        };
+    }
     match manager::retrieve_assets(&redis_client).await {
         Ok(assets) => Ok(HttpResponse::Ok().json(assets)),
         Err(e) => {
@@ -37,9 +56,21 @@ async fn retrieve_assets(redis_client: web::Data<Arc<Client>>) -> ActixResult<im
 }
 
 #[post("/refresh-assets")]
-async fn refresh_assets(redis_client: web::Data<Arc<Client>>) -> ActixResult<impl Responder> {
+async fn refresh_assets(
+    redis_client: web::Data<Arc<Client>>,
+    provider: web::Data<Arc<dyn MusicProvider>>,
+    dictionary: web::Data<Arc<dyn Dictionary>>,
+    options: web::Query<manager::RefreshOptions>,
+) -> ActixResult<impl Responder> {
     info!("refreshing assets...");
-    match manager::refresh_assets(&redis_client).await {
+    match manager::refresh_assets(
+        &redis_client,
+        provider.as_ref().as_ref(),
+        dictionary.as_ref().as_ref(),
+        &options,
+    )
+    .await
+    {
         Ok(_) => Ok(HttpResponse::Ok().json("Assets refreshed successfully")),
         Err(e) => {
             error!("Error refreshing assets: {e}");
@@ -48,26 +79,201 @@ async fn refresh_assets(redis_client: web::Data<Arc<Client>>) -> ActixResult<imp
     }
 }
 
+#[get("/history")]
+async fn history(query: web::Query<manager::HistoryQuery>) -> ActixResult<impl Responder> {
+    info!("retrieving history...");
+    if query.summary {
+        return match manager::history_summaries(&query).await {
+            Ok(summaries) => Ok(HttpResponse::Ok().json(summaries)),
+            Err(e) => {
+                error!("Error retrieving history summaries: {e}");
+                Ok(
+                    HttpResponse::InternalServerError()
+                        .json("Encountered error retrieving history"),
+                )
+            }
+        };
+    }
+    match manager::history(&query).await {
+        Ok(assets) => Ok(HttpResponse::Ok().json(assets)),
+        Err(e) => {
+            error!("Error retrieving history: {e}");
+            Ok(HttpResponse::InternalServerError().json("Encountered error retrieving history"))
+        }
+    }
+}
+
+#[get("/mashups/{id}/cover")]
+async fn mashed_cover(path: web::Path<i64>) -> ActixResult<impl Responder> {
+    let id = path.into_inner();
+    info!("retrieving cover of mashup {}...", id);
+    match cover::stored_cover(id).await {
+        Ok(Some(image)) => Ok(HttpResponse::Ok().content_type("image/jpeg").body(image)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("No cover for this mashup")),
+        Err(e) => {
+            error!("Error retrieving mashed cover: {e}");
+            Ok(HttpResponse::InternalServerError().json("Encountered error retrieving cover"))
+        }
+    }
+}
+
+#[get("/mashups/{id}/tracks/{track}/cover")]
+async fn track_cover(
+    redis_client: web::Data<Arc<Client>>,
+    provider: web::Data<Arc<dyn MusicProvider>>,
+    path: web::Path<(i64, u8)>,
+    query: web::Query<artwork::CoverQuery>,
+) -> ActixResult<impl Responder> {
+    let (id, track) = path.into_inner();
+    info!("retrieving cover of track {} of mashup {}...", track, id);
+    let (size, format) = (query.size(), query.format());
+    if !artwork::COVER_SIZES.contains(&size) {
+        return Ok(HttpResponse::BadRequest().json("Unsupported cover size"));
+    }
+    if track != 1 && track != 2 {
+        return Ok(HttpResponse::BadRequest().json("Track must be 1 or 2"));
+    }
+    let source = match manager::select_cover_source(id, track).await {
+        Ok(Some(source)) => source,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("No such mashup")),
+        Err(e) => {
+            error!("Error retrieving mashup: {e}");
+            return Ok(
+                HttpResponse::InternalServerError().json("Encountered error retrieving cover")
+            );
+        }
+    };
+    let provider = match providers::by_name(source.provider(), &provider) {
+        Ok(provider) => provider,
+        Err(e) => {
+            error!("Error resolving provider of track cover: {e}");
+            return Ok(HttpResponse::NotFound().json("Cover is no longer available"));
+        }
+    };
+    match artwork::proxied_cover(&redis_client, provider.as_ref(), &source, size, format).await {
+        Ok(image) => Ok(HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((header::CACHE_CONTROL, "public, max-age=604800, immutable"))
+            .body(image)),
+        Err(e) => {
+            error!("Error retrieving track cover: {e}");
+            Ok(HttpResponse::InternalServerError().json("Encountered error retrieving cover"))
+        }
+    }
+}
+
+async fn respond_daily_puzzle(
+    provider: &dyn MusicProvider,
+    dictionary: &dyn Dictionary,
+    date: NaiveDate,
+) -> ActixResult<HttpResponse> {
+    match daily::daily_puzzle(provider, dictionary, date).await {
+        Ok(Some(puzzle)) => Ok(HttpResponse::Ok().json(puzzle)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("No daily mashup for this date")),
+        Err(e) => {
+            error!("Error retrieving daily mashup: {e}");
+            Ok(HttpResponse::InternalServerError()
+                .json("Encountered error retrieving daily mashup"))
+        }
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+#[get("/daily")]
+async fn todays_daily(
+    provider: web::Data<Arc<dyn MusicProvider>>,
+    dictionary: web::Data<Arc<dyn Dictionary>>,
+) -> ActixResult<impl Responder> {
+    info!("retrieving today's daily mashup...");
+    respond_daily_puzzle(
+        provider.as_ref().as_ref(),
+        dictionary.as_ref().as_ref(),
+        daily::today(),
+    )
+    .await
+}
+
+#[get("/daily/{date}")]
+async fn daily_by_date(
+    provider: web::Data<Arc<dyn MusicProvider>>,
+    dictionary: web::Data<Arc<dyn Dictionary>>,
+    path: web::Path<String>,
+) -> ActixResult<impl Responder> {
+    info!("retrieving daily mashup for {}...", path);
+    match parse_date(&path) {
+        Some(date) => {
+            respond_daily_puzzle(
+                provider.as_ref().as_ref(),
+                dictionary.as_ref().as_ref(),
+                date,
+            )
+            .await
+        }
+        None => Ok(HttpResponse::BadRequest().json("Invalid date")),
+    }
+}
+
+#[post("/daily/{date}/guess")]
+async fn guess_daily(
+    provider: web::Data<Arc<dyn MusicProvider>>,
+    dictionary: web::Data<Arc<dyn Dictionary>>,
+    path: web::Path<String>,
+    guess: web::Json<daily::DailyGuess>,
+) -> ActixResult<impl Responder> {
+    let Some(date) = parse_date(&path) else {
+        return Ok(HttpResponse::BadRequest().json("Invalid date"));
+    };
+    match daily::guess_daily(
+        provider.as_ref().as_ref(),
+        dictionary.as_ref().as_ref(),
+        date,
+        &guess,
+    )
+    .await
+    {
+        Ok(Some(result)) => Ok(HttpResponse::Ok().json(result)),
+        Ok(None) => Ok(HttpResponse::NotFound().json("No daily mashup for this date")),
+        Err(e) => {
+            error!("Error checking daily guess: {e}");
+            Ok(HttpResponse::InternalServerError().json("Encountered error checking guess"))
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::Builder::new()
         .filter_level(log::LevelFilter::Info)
         .init();
     dotenv::dotenv().ok();
+    let config = config::init()?;
     let redis_client = Arc::new(get_redis_connection().await?);
+    let provider = providers::from_config(config)?;
+    let dictionary = dictionaries::from_config(config)?;
 
     HttpServer::new(move || {
         App::new()
             .wrap(
                 Cors::default()
                     .allowed_origin("http://localhost:5173")
-                    .allowed_methods(vec!["GET"])
+                    .allowed_methods(vec!["GET", "POST"])
                     .allowed_headers(vec![header::CONTENT_TYPE, header::AUTHORIZATION])
                     .max_age(3600),
             )
             .app_data(web::Data::new(redis_client.clone()))
+            .app_data(web::Data::new(provider.clone()))
+            .app_data(web::Data::new(dictionary.clone()))
             .service(retrieve_assets)
             .service(refresh_assets)
+            .service(history)
+            .service(mashed_cover)
+            .service(track_cover)
+            .service(todays_daily)
+            .service(daily_by_date)
+            .service(guess_daily)
             .service(Files::new("/", "./mashup-hour-frontend/dist").index_file("index.html"))
     })
     .bind(("127.0.0.1", 8080))?
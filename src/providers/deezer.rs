@@ -0,0 +1,205 @@
+use super::{IndexedTrack, MusicProvider, Track};
+use crate::apis::{
+    base::{APIResult, Pagination},
+    deezer as d,
+};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::{debug, info};
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Preview URLs expiring within this many seconds are treated as expired.
+const EXPIRY_MARGIN: u64 = 60;
+
+type SearchResult = APIResult<d::DeezerPaginationResponse<d::TrackList>>;
+
+#[derive(Default)]
+pub struct DeezerProvider {
+    /// The last search and its query, so `track_at` can page through it
+    /// without searching again.
+    last_search: Mutex<Option<(String, Arc<SearchResult>)>>,
+}
+
+impl DeezerProvider {
+    fn cached_search(&self, query: &str) -> Option<Arc<SearchResult>> {
+        match &*self.last_search.lock().ok()? {
+            Some((cached, search)) if cached == query => Some(search.clone()),
+            _ => None,
+        }
+    }
+
+    async fn search_tracks(&self, query: &str) -> Result<Arc<SearchResult>> {
+        if let Some(search) = self.cached_search(query) {
+            return Ok(search);
+        }
+        let search = Arc::new(d::search_tracks(query).await?);
+        if let Ok(mut last_search) = self.last_search.lock() {
+            *last_search = Some((query.to_string(), search.clone()));
+        }
+        Ok(search)
+    }
+}
+
+impl From<d::Track> for Track {
+    fn from(track: d::Track) -> Self {
+        Self {
+            id: track.id,
+            title: track.title,
+            full_title: track.full_title,
+            artist: track.artist.name,
+            album_title: track.album.title,
+            cover_url: track.album.cover_url,
+            preview_url: track.preview_url,
+        }
+    }
+}
+
+fn find_index_with_preview(tracks: &d::TrackList, start_index: usize) -> Result<usize> {
+    if start_index >= tracks.len() {
+        return Err(Error::IndexError {
+            index: start_index,
+            length: tracks.len(),
+        });
+    }
+    for (i, track) in tracks[start_index..].iter().enumerate() {
+        if !track.preview_url.is_empty() {
+            debug!(
+                "Found track '{}' positions from start index '{}'",
+                i, start_index
+            );
+            return Ok(start_index + i);
+        }
+    }
+    Err(Error::CriticalError("No track with preview".into()))
+}
+
+/// Picks the first track with a preview at or after `index` from the page
+/// of results containing it, along with its index in the whole result set.
+fn track_on_page(mut tracks: d::TrackList, index: u64, page_limit: u64) -> Result<IndexedTrack> {
+    let start_index_of_page = index % page_limit;
+    let index_on_page = find_index_with_preview(&tracks, start_index_of_page as usize)?;
+    let track = tracks.swap_remove(index_on_page);
+    Ok(IndexedTrack {
+        index: index + index_on_page as u64 - start_index_of_page,
+        track: track.into(),
+    })
+}
+
+fn is_expired(preview_url: &str) -> bool {
+    let Some(expiry) = d::preview_expiry(preview_url) else {
+        return false;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    expiry <= now + EXPIRY_MARGIN
+}
+
+/// Re-fetches the track by ID to get a freshly signed preview URL.
+async fn resigned_preview_url(track_id: u64) -> Result<String> {
+    info!("Re-signing preview URL for track {}", track_id);
+    let track = d::get_track(track_id).await?.response;
+    if track.preview_url.is_empty() {
+        return Err(Error::CriticalError(format!(
+            "Track {track_id} no longer has a preview"
+        )));
+    }
+    Ok(track.preview_url)
+}
+
+#[async_trait]
+impl MusicProvider for DeezerProvider {
+    fn name(&self) -> &'static str {
+        "deezer"
+    }
+
+    async fn search(&self, query: &str) -> Result<u64> {
+        Ok(self.search_tracks(query).await?.response.total)
+    }
+
+    async fn track_at(&self, query: &str, index: u64) -> Result<IndexedTrack> {
+        let search = self.search_tracks(query).await?;
+        let page = search.get_page_from_index(&index).await?;
+        track_on_page(page.response.data, index, search.response.page_limit())
+    }
+
+    async fn preview(&self, track: &Track) -> Result<Bytes> {
+        // Tracks rebuilt from stored assets carry no preview URL
+        let url = if track.preview_url.is_empty() || is_expired(&track.preview_url) {
+            resigned_preview_url(track.id).await?
+        } else {
+            track.preview_url.clone()
+        };
+        match d::download(&url).await {
+            Err(Error::ResponseError {
+                status_code: 403, ..
+            }) => d::download(&resigned_preview_url(track.id).await?).await,
+            result => result,
+        }
+    }
+
+    async fn cover(&self, track: &Track) -> Result<Bytes> {
+        d::download(&track.cover_url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: u64, preview_url: &str) -> d::Track {
+        d::Track {
+            id,
+            title: format!("Track {id}"),
+            full_title: format!("Track {id}"),
+            preview_url: preview_url.into(),
+            artist: d::Artist {
+                name: "Artist".into(),
+            },
+            album: d::Album {
+                title: "Album".into(),
+                cover_url: String::new(),
+            },
+        }
+    }
+
+    /// Result `n` of the whole search has id `n`.
+    fn page_containing(index: u64, page_limit: u64) -> d::TrackList {
+        let start = index - index % page_limit;
+        (start..start + page_limit)
+            .map(|id| track(id, if id % 3 == 0 { "" } else { "preview.mp3" }))
+            .collect()
+    }
+
+    #[test]
+    fn returns_the_requested_index() {
+        for index in [1, 26, 1000, 9_998] {
+            let picked = track_on_page(page_containing(index, 25), index, 25).unwrap();
+            assert_eq!(picked.index, index);
+            assert_eq!(picked.track.id, index);
+        }
+    }
+
+    #[test]
+    fn skips_tracks_without_previews() {
+        let picked = track_on_page(page_containing(1002, 25), 1002, 25).unwrap();
+        assert_eq!(picked.index, 1003);
+        assert_eq!(picked.track.id, 1003);
+    }
+
+    #[test]
+    fn pages_by_result_offset() {
+        let page = d::DeezerPaginationResponse {
+            data: d::TrackList::new(),
+            next: None,
+            total: 10_000,
+        };
+        let url = page.get_pagination_url("https://api.deezer.com/search/track?q=love", &40);
+        assert_eq!(url, "https://api.deezer.com/search/track?q=love&index=1000");
+    }
+}
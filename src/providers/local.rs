@@ -0,0 +1,331 @@
+use super::{IndexedTrack, MusicProvider, Track};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::{info, warn};
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::DecoderOptions,
+    errors::Error as AudioError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey},
+    probe::Hint,
+    units::Time,
+};
+use walkdir::WalkDir;
+
+const AUDIO_EXTENSIONS: [&str; 4] = ["mp3", "flac", "ogg", "oga"];
+const PREVIEW_SECONDS: u64 = 30;
+
+/// Previews are downsampled by a whole factor to no less than this rate, so a
+/// 30 s excerpt of a 44.1 kHz file is a 1.3 MB WAV rather than 2.6 MB.
+const PREVIEW_MIN_RATE: u32 = 16_000;
+
+struct LocalTrack {
+    path: PathBuf,
+    track: Track,
+    words: Vec<String>,
+}
+
+/// Serves tracks from a directory of local audio files, matching search
+/// queries against the words in each file's title, artist and album tags.
+pub struct LocalProvider {
+    tracks: Vec<LocalTrack>,
+
+    /// Every distinct tag word, sorted so seeded picks are stable.
+    vocabulary: Arc<[String]>,
+}
+
+impl LocalProvider {
+    pub fn scan(dir: &Path) -> Result<Self> {
+        let mut tracks = Vec::new();
+        // Sorted so track indices, and therefore seeded picks, are stable
+        for entry in WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if !entry.file_type().is_file() || !is_audio_file(path) {
+                continue;
+            }
+            match index_file(dir, path) {
+                Ok(track) => tracks.push(track),
+                Err(err) => warn!("Skipping '{}': {}", path.display(), err),
+            }
+        }
+        info!(
+            "Indexed {} local tracks in '{}'",
+            tracks.len(),
+            dir.display()
+        );
+        // Every search would come back empty
+        if tracks.is_empty() {
+            return Err(Error::Custom(format!(
+                "No playable tracks in '{}'",
+                dir.display()
+            )));
+        }
+        let vocabulary: BTreeSet<String> = tracks
+            .iter()
+            .flat_map(|t| t.words.iter().cloned())
+            .collect();
+        Ok(Self {
+            tracks,
+            vocabulary: vocabulary.into_iter().collect(),
+        })
+    }
+
+    fn matches<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a LocalTrack> {
+        let query = query.to_lowercase();
+        self.tracks.iter().filter(move |t| t.words.contains(&query))
+    }
+
+    fn find(&self, track: &Track) -> Result<&LocalTrack> {
+        self.tracks
+            .iter()
+            .find(|t| t.track.id == track.id)
+            .ok_or(Error::Custom(format!("Unknown local track '{}'", track.id)))
+    }
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// FNV-1a, so track IDs stay stable across restarts and Rust versions.
+fn path_id(path: &Path) -> u64 {
+    path.to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn open_format(path: &Path) -> Result<(Box<dyn FormatReader>, Option<MetadataRevision>)> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    // Tags may live in the container itself or ahead of it (e.g. ID3v2)
+    let revision = probed
+        .format
+        .metadata()
+        .current()
+        .cloned()
+        .or_else(|| probed.metadata.get()?.current().cloned());
+    Ok((probed.format, revision))
+}
+
+fn tag_value(revision: Option<&MetadataRevision>, key: StandardTagKey) -> Option<String> {
+    revision?
+        .tags()
+        .iter()
+        .find(|tag| tag.std_key == Some(key))
+        .map(|tag| tag.value.to_string())
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn index_file(root: &Path, path: &Path) -> Result<LocalTrack> {
+    let (_, revision) = open_format(path)?;
+    let revision = revision.as_ref();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let title = tag_value(revision, StandardTagKey::TrackTitle).unwrap_or(stem);
+    let artist =
+        tag_value(revision, StandardTagKey::Artist).unwrap_or("Unknown Artist".to_string());
+    let album_title =
+        tag_value(revision, StandardTagKey::Album).unwrap_or("Unknown Album".to_string());
+
+    let words = format!("{title} {artist} {album_title}")
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(String::from)
+        .collect();
+
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    Ok(LocalTrack {
+        path: path.to_path_buf(),
+        track: Track {
+            id: path_id(relative),
+            title: title.clone(),
+            full_title: title,
+            artist,
+            album_title,
+            cover_url: String::new(),
+            preview_url: String::new(),
+        },
+        words,
+    })
+}
+
+fn read_cover(path: &Path) -> Result<Bytes> {
+    let (_, revision) = open_format(path)?;
+    let visuals = revision.as_ref().map(|r| r.visuals()).unwrap_or_default();
+    visuals
+        .iter()
+        .find(|v| v.usage == Some(StandardVisualKey::FrontCover))
+        .or(visuals.first())
+        .map(|v| Bytes::copy_from_slice(&v.data))
+        .ok_or(Error::Custom(format!("No cover in '{}'", path.display())))
+}
+
+/// Decodes a mono excerpt of up to `PREVIEW_SECONDS` starting a third of the
+/// way into the track, downsamples it towards `PREVIEW_MIN_RATE` and encodes
+/// it as 16-bit PCM WAV.
+fn read_excerpt(path: &Path) -> Result<Bytes> {
+    let (mut format, _) = open_format(path)?;
+    let track = format
+        .default_track()
+        .ok_or(Error::Custom(format!("No audio in '{}'", path.display())))?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.unwrap_or(44_100);
+    let duration = track.codec_params.n_frames.unwrap_or(0) / sample_rate as u64;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let start = if duration > PREVIEW_SECONDS * 2 {
+        duration / 3
+    } else {
+        0
+    };
+    if start > 0 {
+        format.seek(
+            SeekMode::Coarse,
+            SeekTo::Time {
+                time: Time::new(start, 0.0),
+                track_id: Some(track_id),
+            },
+        )?;
+    }
+
+    let factor = (sample_rate / PREVIEW_MIN_RATE).max(1) as usize;
+    let max_samples = (PREVIEW_SECONDS * sample_rate as u64) as usize / factor;
+    let mut samples: Vec<i16> = Vec::with_capacity(max_samples);
+    let mut pending: Vec<f32> = Vec::with_capacity(factor);
+
+    while samples.len() < max_samples {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(AudioError::IoError(_)) => break,
+            Err(err) => return Err(err.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(AudioError::DecodeError(_)) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let channels = decoded.spec().channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        buffer.copy_interleaved_ref(decoded);
+
+        for frame in buffer.samples().chunks(channels) {
+            pending.push(frame.iter().sum::<f32>() / channels as f32);
+            // Averaging each group of frames doubles as a crude low-pass
+            if pending.len() == factor {
+                let mono = pending.drain(..).sum::<f32>() / factor as f32;
+                samples.push((mono.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+            }
+        }
+    }
+    samples.truncate(max_samples);
+    Ok(encode_wav(&samples, sample_rate / factor as u32))
+}
+
+fn encode_wav(samples: &[i16], sample_rate: u32) -> Bytes {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    Bytes::from(wav)
+}
+
+async fn blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Error::CriticalError(e.to_string()))?
+}
+
+#[async_trait]
+impl MusicProvider for LocalProvider {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn search(&self, query: &str) -> Result<u64> {
+        Ok(self.matches(query).count() as u64)
+    }
+
+    async fn track_at(&self, query: &str, index: u64) -> Result<IndexedTrack> {
+        match self.matches(query).nth(index as usize) {
+            Some(local) => Ok(IndexedTrack {
+                index,
+                track: local.track.clone(),
+            }),
+            None => Err(Error::IndexError {
+                index: index as usize,
+                length: self.matches(query).count(),
+            }),
+        }
+    }
+
+    async fn preview(&self, track: &Track) -> Result<Bytes> {
+        let path = self.find(track)?.path.clone();
+        blocking(move || read_excerpt(&path)).await
+    }
+
+    fn preview_type(&self) -> &'static str {
+        "audio/wav"
+    }
+
+    async fn cover(&self, track: &Track) -> Result<Bytes> {
+        let path = self.find(track)?.path.clone();
+        blocking(move || read_cover(&path)).await
+    }
+
+    fn vocabulary(&self) -> Option<Arc<[String]>> {
+        Some(self.vocabulary.clone())
+    }
+}
@@ -0,0 +1,81 @@
+mod deezer;
+mod local;
+
+pub use deezer::DeezerProvider;
+pub use local::LocalProvider;
+
+use crate::{config::Config, Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::{path::Path, sync::Arc};
+
+/// A provider-neutral track, as returned by any `MusicProvider`.
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub id: u64,
+    pub title: String,
+    pub full_title: String,
+    pub artist: String,
+    pub album_title: String,
+    pub cover_url: String,
+    pub preview_url: String,
+}
+
+pub struct IndexedTrack {
+    pub index: u64,
+    pub track: Track,
+}
+
+#[async_trait]
+pub trait MusicProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns the total number of tracks matching `query`.
+    async fn search(&self, query: &str) -> Result<u64>;
+
+    /// Fetches the first track with a preview at or after `index` in the
+    /// results for `query`, along with its true index.
+    async fn track_at(&self, query: &str, index: u64) -> Result<IndexedTrack>;
+
+    async fn preview(&self, track: &Track) -> Result<Bytes>;
+
+    /// The MIME type of the bytes `preview` returns.
+    fn preview_type(&self) -> &'static str {
+        "audio/mpeg"
+    }
+
+    async fn cover(&self, track: &Track) -> Result<Bytes>;
+
+    /// Every word a search can match, for providers that know it up front.
+    /// Random dictionary words rarely match a small library.
+    fn vocabulary(&self) -> Option<Arc<[String]>> {
+        None
+    }
+}
+
+pub fn from_config(config: &Config) -> Result<Arc<dyn MusicProvider>> {
+    match config.music_provider.as_str() {
+        "deezer" => Ok(Arc::new(DeezerProvider::default())),
+        "local" => match &config.local_library_dir {
+            Some(dir) => Ok(Arc::new(LocalProvider::scan(Path::new(dir))?)),
+            None => Err(Error::Custom(
+                "LOCAL_LIBRARY_DIR is required for the local provider".into(),
+            )),
+        },
+        other => Err(Error::Custom(format!("Unknown music provider '{other}'"))),
+    }
+}
+
+/// The provider a stored track came from, which need not be the configured
+/// one. Only providers that need no setup can be created on demand.
+pub fn by_name(name: &str, configured: &Arc<dyn MusicProvider>) -> Result<Arc<dyn MusicProvider>> {
+    if name == configured.name() {
+        return Ok(configured.clone());
+    }
+    match name {
+        "deezer" => Ok(Arc::new(DeezerProvider::default())),
+        other => Err(Error::Custom(format!(
+            "Music provider '{other}' is not available"
+        ))),
+    }
+}